@@ -0,0 +1,78 @@
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+struct ChunkTextures {
+    tile_uv_size: f32,
+    layered: u32,
+    alpha_cutoff: f32,
+    daylight: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var<uniform> chunk_textures: ChunkTextures;
+@group(#{MATERIAL_BIND_GROUP}) @binding(101) var block_texture: texture_2d_array<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(102) var block_sampler: sampler;
+
+// uv counts blocks across the quad, tile is the origin of the tile in the atlas
+// or the layer in x for the texture array
+fn sample_block(uv: vec2<f32>, tile: vec2<f32>) -> vec4<f32> {
+    var scale = chunk_textures.tile_uv_size;
+    var coords = tile + fract(uv) * scale;
+    var layer = 0;
+
+    if chunk_textures.layered != 0u {
+        scale = 1.0;
+        coords = fract(uv);
+        layer = i32(tile.x);
+    }
+
+    // gradients of the unwrapped uv, fract would make them jump at every block edge
+    return textureSampleGrad(block_texture, block_sampler, coords, layer, dpdx(uv) * scale, dpdy(uv) * scale);
+}
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+#ifdef VERTEX_COLORS
+    // vertex alpha is the share of the light that comes from blocks, the rest is sky light
+    // and follows the time of day. it got multiplied into the material alpha, take it back out
+    let color = pbr_input.material.base_color;
+    pbr_input.material.base_color = vec4(
+        color.rgb * max(chunk_textures.daylight, in.color.a),
+        color.a / max(in.color.a, 0.0001),
+    );
+#endif
+
+#ifdef VERTEX_UVS_B
+    pbr_input.material.base_color *= sample_block(in.uv, in.uv_b);
+#endif
+
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+
+    return out;
+}
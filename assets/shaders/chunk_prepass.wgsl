@@ -0,0 +1,75 @@
+#import bevy_pbr::{
+    pbr_prepass_functions,
+    prepass_io::VertexOutput,
+}
+
+#ifdef PREPASS_FRAGMENT
+#import bevy_pbr::prepass_io::FragmentOutput
+#endif
+
+struct ChunkTextures {
+    tile_uv_size: f32,
+    layered: u32,
+    alpha_cutoff: f32,
+    daylight: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var<uniform> chunk_textures: ChunkTextures;
+@group(#{MATERIAL_BIND_GROUP}) @binding(101) var block_texture: texture_2d_array<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(102) var block_sampler: sampler;
+
+// uv counts blocks across the quad, tile is the origin of the tile in the atlas
+// or the layer in x for the texture array
+fn sample_block(uv: vec2<f32>, tile: vec2<f32>) -> vec4<f32> {
+    var scale = chunk_textures.tile_uv_size;
+    var coords = tile + fract(uv) * scale;
+    var layer = 0;
+
+    if chunk_textures.layered != 0u {
+        scale = 1.0;
+        coords = fract(uv);
+        layer = i32(tile.x);
+    }
+
+    // gradients of the unwrapped uv, fract would make them jump at every block edge
+    return textureSampleGrad(block_texture, block_sampler, coords, layer, dpdx(uv) * scale, dpdy(uv) * scale);
+}
+
+// cutout texels don't cast shadows or write depth
+fn alpha_discard(in: VertexOutput) {
+#ifdef VERTEX_UVS_A
+#ifdef VERTEX_UVS_B
+    if sample_block(in.uv, in.uv_b).a < chunk_textures.alpha_cutoff {
+        discard;
+    }
+#endif
+#endif
+}
+
+#ifdef PREPASS_FRAGMENT
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    alpha_discard(in);
+
+    var out: FragmentOutput;
+
+#ifdef UNCLIPPED_DEPTH_ORTHO_EMULATION
+    out.frag_depth = in.unclipped_depth;
+#endif
+
+#ifdef NORMAL_PREPASS
+    out.normal = vec4(in.world_normal * 0.5 + vec3(0.5), 1.0);
+#endif
+
+#ifdef MOTION_VECTOR_PREPASS
+    out.motion_vector = pbr_prepass_functions::calculate_motion_vector(in.world_position, in.previous_world_position);
+#endif
+
+    return out;
+}
+#else
+@fragment
+fn fragment(in: VertexOutput) {
+    alpha_discard(in);
+}
+#endif
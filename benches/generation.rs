@@ -0,0 +1,53 @@
+// `cargo bench --bench generation [-- --seed <value>]`: compares rebuilding the generator for
+// every chunk (the old behaviour) with sharing one, prints chunks/second for both
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use bevy::math::IVec2;
+
+use minecraft_clone::engine::world::generator::WorldGenerator;
+use minecraft_clone::engine::world::seed::WorldSeed;
+
+const RADIUS: i32 = 4;
+const RUNS: u32 = 3;
+
+// best of a few runs, the first one also pays for warming up caches
+fn measure(mut run: impl FnMut()) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    let seed = WorldSeed::from_args().unwrap_or_default();
+
+    let coords: Vec<IVec2> = (-RADIUS..RADIUS)
+        .flat_map(|x| (-RADIUS..RADIUS).map(move |z| IVec2::new(x, z)))
+        .collect();
+
+    let per_chunk = measure(|| {
+        for coord in &coords {
+            let generator = WorldGenerator::new(seed);
+            black_box(generator.generate_chunk(*coord));
+        }
+    });
+
+    let shared = measure(|| {
+        let generator = WorldGenerator::new(seed);
+        for coord in &coords {
+            black_box(generator.generate_chunk(*coord));
+        }
+    });
+
+    let rate = |elapsed: Duration| coords.len() as f64 / elapsed.as_secs_f64();
+
+    println!("generated {} chunks with seed {}", coords.len(), seed.0);
+    println!("  rebuilt per chunk: {:>8.1} chunks/s", rate(per_chunk));
+    println!("  shared generator:  {:>8.1} chunks/s", rate(shared));
+}
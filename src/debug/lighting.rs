@@ -0,0 +1,44 @@
+use bevy::input::keyboard::KeyCode;
+use bevy::prelude::*;
+
+use crate::engine::world::chunk_meshing::LightingMode;
+
+pub struct LightingDebugPlugin {
+    toggle_key: KeyCode,
+}
+
+#[derive(Resource)]
+struct LightingDebugConfig {
+    toggle_key: KeyCode,
+}
+
+impl Default for LightingDebugPlugin {
+    fn default() -> Self {
+        Self {
+            toggle_key: KeyCode::KeyL,
+        }
+    }
+}
+
+impl Plugin for LightingDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LightingDebugConfig {
+            toggle_key: self.toggle_key,
+        })
+        .add_systems(Update, toggle_lighting);
+    }
+}
+
+fn toggle_lighting(
+    conf: Res<LightingDebugConfig>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut lighting: ResMut<LightingMode>,
+) {
+    if keys.just_pressed(conf.toggle_key) {
+        *lighting = match *lighting {
+            LightingMode::Flat => LightingMode::Smooth,
+            LightingMode::Smooth => LightingMode::Flat,
+        };
+        info!("Lighting: {:?}", *lighting);
+    }
+}
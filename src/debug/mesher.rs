@@ -0,0 +1,44 @@
+use bevy::input::keyboard::KeyCode;
+use bevy::prelude::*;
+
+use crate::engine::world::chunk_meshing::ChunkMesher;
+
+pub struct MesherDebugPlugin {
+    toggle_key: KeyCode,
+}
+
+#[derive(Resource)]
+struct MesherDebugConfig {
+    toggle_key: KeyCode,
+}
+
+impl Default for MesherDebugPlugin {
+    fn default() -> Self {
+        Self {
+            toggle_key: KeyCode::KeyG,
+        }
+    }
+}
+
+impl Plugin for MesherDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MesherDebugConfig {
+            toggle_key: self.toggle_key,
+        })
+        .add_systems(Update, toggle_mesher);
+    }
+}
+
+fn toggle_mesher(
+    conf: Res<MesherDebugConfig>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut mesher: ResMut<ChunkMesher>,
+) {
+    if keys.just_pressed(conf.toggle_key) {
+        *mesher = match *mesher {
+            ChunkMesher::Naive => ChunkMesher::Greedy,
+            ChunkMesher::Greedy => ChunkMesher::Naive,
+        };
+        info!("Mesher: {:?}", *mesher);
+    }
+}
@@ -1 +1,3 @@
+pub mod lighting;
+pub mod mesher;
 pub mod wireframe;
@@ -1,30 +1,121 @@
+use super::resource_pack::ResourcePacks;
+use super::texture_animation::{AnimatedTiles, AnimationInfo, animate_tiles};
+use super::world::biome_tint::BiomeTint;
 use super::world::block::BlockTextureId;
 
 use std::collections::HashMap;
 
 use bevy::asset::RenderAssetUsages;
 use bevy::image::ImageSampler;
+use bevy::pbr::{ExtendedMaterial, MaterialExtension};
 use bevy::prelude::*;
 use bevy::render::render_resource::{
-    AddressMode, Extent3d, FilterMode, SamplerDescriptor, TextureDimension, TextureFormat,
+    AddressMode, AsBindGroup, Extent3d, FilterMode, SamplerDescriptor, TextureDimension,
+    TextureFormat, TextureViewDescriptor, TextureViewDimension,
 };
+use bevy::shader::ShaderRef;
 
+// resolution of the built-in textures, pack textures have to be a multiple of it
 pub const TILE_SIZE: u32 = 16;
 
+const CHUNK_SHADER_PATH: &str = "shaders/chunk.wgsl";
+const CHUNK_PREPASS_SHADER_PATH: &str = "shaders/chunk_prepass.wgsl";
+
+const ALPHA_CUTOFF: f32 = 0.5;
+
 pub struct AtlasPlugin;
 
+// how block textures end up on the gpu. picked once, when the textures finish loading
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlockTextureBackend {
+    // everything packed into one 2d texture, nearest filtered and without mipmaps
+    Atlas,
+    // one array layer per texture, so tiles can be mipmapped without bleeding into each other
+    #[default]
+    TextureArray,
+}
+
+// pixels per tile side in the built block texture. every texture is resampled to it
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TileResolution {
+    // the widest texture any pack provided, smaller ones are scaled up
+    #[default]
+    Largest,
+    // rounded up to a power of two, so the mip chain halves cleanly
+    Fixed(u32),
+}
+
+pub type BlockMaterial = ExtendedMaterial<StandardMaterial, ChunkTextures>;
+
+#[derive(Resource)]
+pub struct ChunkMaterial(pub Handle<BlockMaterial>);
+
+// water, ice and anything else blended over the opaque world
 #[derive(Resource)]
-pub struct ChunkMaterial(pub Handle<StandardMaterial>);
+pub struct TranslucentChunkMaterial(pub Handle<BlockMaterial>);
+
+// block textures for the chunk shader. meshes carry uvs in block units so a quad can span
+// several blocks and still repeat its texture, UV_1 says which tile: its top left corner
+// in the atlas, or its layer in x for the texture array
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub struct ChunkTextures {
+    #[uniform(100)]
+    pub tile_uv_size: f32,
+    #[uniform(100)]
+    pub layered: u32,
+    #[uniform(100)]
+    pub alpha_cutoff: f32,
+    // how much of the sky light gets through, follows the time of day
+    #[uniform(100)]
+    pub daylight: f32,
+    #[texture(101, dimension = "2d_array")]
+    #[sampler(102)]
+    pub texture: Handle<Image>,
+}
+
+impl MaterialExtension for ChunkTextures {
+    fn fragment_shader() -> ShaderRef {
+        CHUNK_SHADER_PATH.into()
+    }
 
-#[derive(Debug)]
+    fn deferred_fragment_shader() -> ShaderRef {
+        CHUNK_SHADER_PATH.into()
+    }
+
+    // cutout blocks alpha test in the shadow and depth passes as well
+    fn prepass_fragment_shader() -> ShaderRef {
+        CHUNK_PREPASS_SHADER_PATH.into()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TextureAtlas {
+    backend: BlockTextureBackend,
     tiles_per_row: u32,
     tile_uv_size: f32,
     indices: HashMap<BlockTextureId, u32>,
 }
 
+// where a texture lives in the built block texture
+#[derive(Clone, Copy, Debug)]
+pub enum TileSlot {
+    Atlas {
+        tile_x: u32,
+        tile_y: u32,
+        tiles_per_row: u32,
+        tile_size: u32,
+    },
+    Layer {
+        layer: u32,
+        tile_size: u32,
+    },
+}
+
 #[derive(Resource)]
-pub struct PendingBlockTextures(pub HashMap<BlockTextureId, Handle<Image>>);
+pub struct PendingBlockTextures {
+    pub handles: HashMap<BlockTextureId, Handle<Image>>,
+    pub animations: HashMap<BlockTextureId, AnimationInfo>,
+}
 
 #[derive(Resource)]
 pub struct BlockAtlas {
@@ -33,51 +124,138 @@ pub struct BlockAtlas {
 
 impl Plugin for AtlasPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_texture_loading)
-            .add_systems(Update, try_build_atlas);
+        app.add_plugins(MaterialPlugin::<BlockMaterial>::default())
+            .init_resource::<BlockTextureBackend>()
+            .init_resource::<TileResolution>()
+            .init_resource::<ResourcePacks>()
+            .add_systems(Startup, setup_texture_loading)
+            .add_systems(
+                Update,
+                (
+                    try_build_atlas,
+                    animate_tiles.run_if(resource_exists::<AnimatedTiles>),
+                ),
+            );
+    }
+}
+
+impl TileResolution {
+    fn tile_size(self, widths: impl Iterator<Item = u32>) -> u32 {
+        let size = match self {
+            TileResolution::Largest => widths.max().unwrap_or(TILE_SIZE),
+            TileResolution::Fixed(size) => size,
+        };
+
+        size.max(1).next_power_of_two()
+    }
+}
+
+impl TileSlot {
+    pub fn tile_size(self) -> u32 {
+        match self {
+            TileSlot::Atlas { tile_size, .. } | TileSlot::Layer { tile_size, .. } => tile_size,
+        }
     }
 }
 
 impl TextureAtlas {
-    pub fn uvs(&self, id: BlockTextureId) -> [[f32; 2]; 4] {
+    // what meshes put into UV_1 for this texture, see ChunkTextures
+    pub fn tile_origin(&self, id: BlockTextureId) -> [f32; 2] {
         let index = self.indices[&id];
 
-        let x = index % self.tiles_per_row;
-        let y = index / self.tiles_per_row;
+        match self.backend {
+            BlockTextureBackend::Atlas => {
+                let x = index % self.tiles_per_row;
+                let y = index / self.tiles_per_row;
+
+                [x as f32 * self.tile_uv_size, y as f32 * self.tile_uv_size]
+            }
+            BlockTextureBackend::TextureArray => [index as f32, 0.0],
+        }
+    }
+}
 
-        let s = self.tile_uv_size;
+pub fn setup_texture_loading(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    packs: Res<ResourcePacks>,
+) {
+    let mut handles = HashMap::new();
+    let mut animations = HashMap::new();
 
-        let u_min = x as f32 * s;
-        let u_max = u_min + s;
+    for id in BlockTextureId::get_all() {
+        let path = id.path();
 
-        let v_min = y as f32 * s;
-        let v_max = v_min + s;
+        let Some(texture) = packs.load_texture(&path) else {
+            warn!("no resource pack has a usable {path}");
+            handles.insert(id, images.add(missing_texture()));
+            continue;
+        };
 
-        [
-            [u_max, v_max],
-            [u_min, v_max],
-            [u_min, v_min],
-            [u_max, v_min],
-        ]
+        let mut image = texture.image;
+
+        // tints multiply grayscale textures, the built-in grass top comes painted green
+        if id.tint() == Some(BiomeTint::Grass) {
+            desaturate(&mut image);
+        }
+
+        handles.insert(id, images.add(image));
+        if let Some(animation) = texture.animation {
+            animations.insert(id, animation);
+        }
     }
+
+    commands.insert_resource(PendingBlockTextures {
+        handles,
+        animations,
+    });
 }
 
-fn load_block_textures(asset_server: Res<AssetServer>) -> HashMap<BlockTextureId, Handle<Image>> {
-    BlockTextureId::get_all()
-        .iter()
-        .map(|id| (*id, asset_server.load(id.path())))
-        .collect()
+// keeps the brightest channel, so painted textures keep their brightness
+fn desaturate(image: &mut Image) {
+    let Some(data) = image.data.as_mut() else {
+        return;
+    };
+
+    for pixel in data.chunks_exact_mut(4) {
+        let value = pixel[0].max(pixel[1]).max(pixel[2]);
+        pixel[..3].fill(value);
+    }
 }
 
-pub fn setup_texture_loading(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let handles = load_block_textures(asset_server);
-    commands.insert_resource(PendingBlockTextures(handles));
+// magenta and black checkers, hard to miss in the world
+fn missing_texture() -> Image {
+    let half = TILE_SIZE / 2;
+    let data = (0..TILE_SIZE * TILE_SIZE)
+        .flat_map(|i| {
+            let (x, y) = (i % TILE_SIZE, i / TILE_SIZE);
+            if (x < half) == (y < half) {
+                [248, 0, 248, 255]
+            } else {
+                [0, 0, 0, 255]
+            }
+        })
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: TILE_SIZE,
+            height: TILE_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD,
+    )
 }
 
 pub fn try_build_atlas(
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut materials: ResMut<Assets<BlockMaterial>>,
+    backend: Res<BlockTextureBackend>,
+    resolution: Res<TileResolution>,
     pending: Option<Res<PendingBlockTextures>>,
 ) {
     let pending = match pending {
@@ -85,29 +263,49 @@ pub fn try_build_atlas(
         None => return,
     };
 
-    if !pending.0.values().all(|h| images.get(h).is_some()) {
+    if !pending.handles.values().all(|h| images.get(h).is_some()) {
         return;
     }
 
-    let (mut atlas_image, tiles_per_row, indices) = build_atlas(&images, &pending.0);
+    let tiles: Vec<BlockTile> = BlockTextureId::get_all()
+        .into_iter()
+        .map(|id| BlockTile {
+            id,
+            image: images.get(&pending.handles[&id]).unwrap().clone(),
+            animation: pending.animations.get(&id).cloned(),
+        })
+        .collect();
 
-    atlas_image.sampler = ImageSampler::Descriptor(
-        SamplerDescriptor {
-            mag_filter: FilterMode::Nearest,
-            min_filter: FilterMode::Nearest,
-            mipmap_filter: FilterMode::Nearest,
-            address_mode_u: AddressMode::ClampToEdge,
-            address_mode_v: AddressMode::ClampToEdge,
-            ..default()
-        }
-        .into(),
-    );
+    let tile_size = resolution.tile_size(tiles.iter().map(|tile| tile.image.width()));
+
+    let (image, tiles_per_row, indices, animations) = match *backend {
+        BlockTextureBackend::Atlas => build_atlas(&tiles, tile_size),
+        BlockTextureBackend::TextureArray => build_texture_array(&tiles, tile_size),
+    };
+
+    let texture_handle = images.add(image);
+
+    let mut animated = AnimatedTiles::new(texture_handle.clone());
+    for (info, strip, slot) in animations {
+        animated.add(info, &strip, slot);
+    }
+    if !animated.is_empty() {
+        commands.insert_resource(animated);
+    }
 
-    let atlas_handle = images.add(atlas_image);
+    let tile_uv_size = 1.0 / tiles_per_row as f32;
+    let textures = ChunkTextures {
+        tile_uv_size,
+        layered: (*backend == BlockTextureBackend::TextureArray) as u32,
+        alpha_cutoff: ALPHA_CUTOFF,
+        daylight: 1.0,
+        texture: texture_handle,
+    };
 
     let texture_atlas = TextureAtlas {
+        backend: *backend,
         tiles_per_row,
-        tile_uv_size: 1.0 / tiles_per_row as f32,
+        tile_uv_size,
         indices,
     };
 
@@ -115,47 +313,82 @@ pub fn try_build_atlas(
         texture: texture_atlas,
     });
 
-    let material = materials.add(StandardMaterial {
-        base_color_texture: Some(atlas_handle),
-        perceptual_roughness: 1.0,
-        metallic: 0.0,
-        ..default()
+    // the textures are sampled by the extension, the base only carries the surface settings
+    let material = materials.add(ExtendedMaterial {
+        base: StandardMaterial {
+            perceptual_roughness: 1.0,
+            metallic: 0.0,
+            alpha_mode: AlphaMode::Mask(ALPHA_CUTOFF),
+            ..default()
+        },
+        extension: textures.clone(),
+    });
+
+    let translucent = materials.add(ExtendedMaterial {
+        base: StandardMaterial {
+            base_color: Color::srgba(1.0, 1.0, 1.0, 0.8),
+            perceptual_roughness: 0.3,
+            metallic: 0.0,
+            alpha_mode: AlphaMode::Blend,
+            ..default()
+        },
+        extension: textures,
     });
 
     commands.insert_resource(ChunkMaterial(material));
+    commands.insert_resource(TranslucentChunkMaterial(translucent));
 
     commands.remove_resource::<PendingBlockTextures>();
-    info!("atlas built!");
+    info!("atlas built with {tile_size}px tiles!");
+}
+
+// a texture going into the block texture, a strip of square frames if it's animated
+struct BlockTile {
+    id: BlockTextureId,
+    image: Image,
+    animation: Option<AnimationInfo>,
 }
 
-fn build_atlas(
-    images: &Assets<Image>,
-    handles: &HashMap<BlockTextureId, Handle<Image>>,
-) -> (Image, u32, HashMap<BlockTextureId, u32>) {
-    let count = handles.len() as u32;
+// an animated texture's mcmeta, its frame strip and where it is shown
+type TileAnimation = (AnimationInfo, Vec<u8>, TileSlot);
+
+type BuiltBlockTexture = (Image, u32, HashMap<BlockTextureId, u32>, Vec<TileAnimation>);
+
+fn build_atlas(tiles: &[BlockTile], tile_size: u32) -> BuiltBlockTexture {
+    let count = tiles.len() as u32;
     let tiles_per_row = (count as f32).sqrt().ceil() as u32;
 
-    let atlas_size = tiles_per_row * TILE_SIZE;
+    let atlas_size = tiles_per_row * tile_size;
     let mut atlas_data = vec![0u8; (atlas_size * atlas_size * 4) as usize];
 
     let mut indices = HashMap::new();
+    let mut animations = Vec::new();
 
-    for (i, id) in BlockTextureId::get_all().into_iter().enumerate() {
+    for (i, tile) in tiles.iter().enumerate() {
         let index = i as u32;
 
-        let x = index % tiles_per_row;
-        let y = index / tiles_per_row;
-
-        let handle = &handles[&id];
-        let src = images.get(handle).unwrap();
-        let src_data = src.data.as_ref().expect("Image has no CPU data");
-
-        copy_tile(src_data, &mut atlas_data, x, y, tiles_per_row);
+        let slot = TileSlot::Atlas {
+            tile_x: index % tiles_per_row,
+            tile_y: index / tiles_per_row,
+            tiles_per_row,
+            tile_size,
+        };
+
+        // animated textures are strips of frames, the first one is shown until the
+        // animation system takes over
+        let strip = resample_strip(&tile.image, tile_size);
+        write_tile(&mut atlas_data, slot, &strip);
+
+        if let Some(info) = &tile.animation
+            && strip.len() > frame_len(tile_size)
+        {
+            animations.push((info.clone(), strip, slot));
+        }
 
-        indices.insert(id, index);
+        indices.insert(tile.id, index);
     }
 
-    let image = Image::new(
+    let mut image = Image::new(
         Extent3d {
             width: atlas_size,
             height: atlas_size,
@@ -167,21 +400,331 @@ fn build_atlas(
         RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
     );
 
-    (image, tiles_per_row, indices)
+    image.sampler = ImageSampler::Descriptor(
+        SamplerDescriptor {
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            ..default()
+        }
+        .into(),
+    );
+
+    // the shader only samples arrays, the atlas is a single layer one
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2Array),
+        ..default()
+    });
+
+    (image, tiles_per_row, indices, animations)
 }
 
-fn copy_tile(src: &[u8], dst: &mut [u8], tile_x: u32, tile_y: u32, tiles_per_row: u32) {
-    let atlas_width = tiles_per_row * TILE_SIZE;
+fn build_texture_array(tiles: &[BlockTile], tile_size: u32) -> BuiltBlockTexture {
+    let layers = tiles.len() as u32;
+
+    let mut data = vec![0u8; layer_len(tile_size) * layers as usize];
+
+    let mut indices = HashMap::new();
+    let mut animations = Vec::new();
+
+    for (i, tile) in tiles.iter().enumerate() {
+        let slot = TileSlot::Layer {
+            layer: i as u32,
+            tile_size,
+        };
 
-    for row in 0..TILE_SIZE {
-        let src_offset = (row * TILE_SIZE * 4) as usize;
+        let strip = resample_strip(&tile.image, tile_size);
+        write_tile(&mut data, slot, &strip);
+
+        if let Some(info) = &tile.animation
+            && strip.len() > frame_len(tile_size)
+        {
+            animations.push((info.clone(), strip, slot));
+        }
+
+        indices.insert(tile.id, i as u32);
+    }
+
+    let mut image = Image::new_uninit(
+        Extent3d {
+            width: tile_size,
+            height: tile_size,
+            depth_or_array_layers: layers,
+        },
+        TextureDimension::D2,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+    );
+    image.data = Some(data);
+    image.texture_descriptor.mip_level_count = mip_levels(tile_size);
 
-        let dst_x = tile_x * TILE_SIZE;
-        let dst_y = tile_y * TILE_SIZE + row;
+    // anisotropic filtering needs linear filtering all the way
+    image.sampler = ImageSampler::Descriptor(
+        SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            anisotropy_clamp: 16,
+            ..default()
+        }
+        .into(),
+    );
+
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2Array),
+        ..default()
+    });
+
+    (image, 1, indices, animations)
+}
+
+// tile_size is a power of two, so every level halves down to 1x1
+fn mip_levels(tile_size: u32) -> u32 {
+    tile_size.ilog2() + 1
+}
+
+fn frame_len(tile_size: u32) -> usize {
+    (tile_size * tile_size * 4) as usize
+}
+
+// bytes of one array layer including its mip chain
+fn layer_len(tile_size: u32) -> usize {
+    (0..mip_levels(tile_size))
+        .map(|level| frame_len(tile_size >> level))
+        .sum()
+}
+
+// nearest neighbour scales the frames of `image` to tile_size, keeping them stacked
+fn resample_strip(image: &Image, tile_size: u32) -> Vec<u8> {
+    let src = image.data.as_ref().expect("Image has no CPU data");
+    let width = image.width();
+
+    if width == tile_size {
+        return src.clone();
+    }
+
+    let frames = image.height() / width;
+    let mut strip = Vec::with_capacity(frame_len(tile_size) * frames as usize);
+
+    for frame in 0..frames {
+        for y in 0..tile_size {
+            let src_y = frame * width + y * width / tile_size;
+
+            for x in 0..tile_size {
+                let offset = ((src_y * width + x * width / tile_size) * 4) as usize;
+                strip.extend_from_slice(&src[offset..offset + 4]);
+            }
+        }
+    }
+
+    strip
+}
+
+// writes the first frame of `src` into `slot`
+pub fn write_tile(dst: &mut [u8], slot: TileSlot, src: &[u8]) {
+    match slot {
+        TileSlot::Atlas {
+            tile_x,
+            tile_y,
+            tiles_per_row,
+            tile_size,
+        } => copy_tile(src, dst, tile_x, tile_y, tiles_per_row, tile_size),
+        TileSlot::Layer { layer, tile_size } => {
+            let len = layer_len(tile_size);
+            let start = layer as usize * len;
+            write_mip_chain(&mut dst[start..start + len], src, tile_size);
+        }
+    }
+}
+
+// layers are stored mip after mip, each level a 2x2 box filter of the one before
+fn write_mip_chain(dst: &mut [u8], src: &[u8], tile_size: u32) {
+    let base_len = frame_len(tile_size);
+    dst[..base_len].copy_from_slice(&src[..base_len]);
+
+    let mut offset = 0;
+    let mut size = tile_size as usize;
+
+    while size > 1 {
+        let half = size / 2;
+        let next = offset + size * size * 4;
+
+        for y in 0..half {
+            for x in 0..half {
+                for c in 0..4 {
+                    let at = |dx: usize, dy: usize| {
+                        dst[offset + (((y * 2 + dy) * size + x * 2 + dx) * 4) + c] as u32
+                    };
+                    let sum = at(0, 0) + at(1, 0) + at(0, 1) + at(1, 1);
+
+                    dst[next + (y * half + x) * 4 + c] = (sum / 4) as u8;
+                }
+            }
+        }
+
+        offset = next;
+        size = half;
+    }
+}
+
+fn copy_tile(
+    src: &[u8],
+    dst: &mut [u8],
+    tile_x: u32,
+    tile_y: u32,
+    tiles_per_row: u32,
+    tile_size: u32,
+) {
+    let atlas_width = tiles_per_row * tile_size;
+    let row_len = (tile_size * 4) as usize;
+
+    for row in 0..tile_size {
+        let src_offset = row as usize * row_len;
+
+        let dst_x = tile_x * tile_size;
+        let dst_y = tile_y * tile_size + row;
 
         let dst_offset = ((dst_y * atlas_width + dst_x) * 4) as usize;
 
-        dst[dst_offset..dst_offset + (TILE_SIZE * 4) as usize]
-            .copy_from_slice(&src[src_offset..src_offset + (TILE_SIZE * 4) as usize]);
+        dst[dst_offset..dst_offset + row_len]
+            .copy_from_slice(&src[src_offset..src_offset + row_len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every texel says where it came from: its x and y in the frame, which texture, which frame
+    fn texel(marker: u8, frame: u32, x: u32, y: u32) -> [u8; 4] {
+        [x as u8, y as u8, marker, frame as u8]
+    }
+
+    fn synthetic(marker: u8, size: u32, frames: u32) -> Image {
+        let mut data = Vec::new();
+
+        for frame in 0..frames {
+            for y in 0..size {
+                for x in 0..size {
+                    data.extend_from_slice(&texel(marker, frame, x, y));
+                }
+            }
+        }
+
+        Image::new(
+            Extent3d {
+                width: size,
+                height: size * frames,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::MAIN_WORLD,
+        )
+    }
+
+    // a 16px texture, a 32px one and a 16px animation of three frames
+    fn tiles() -> Vec<BlockTile> {
+        let animation = AnimationInfo::parse(r#"{ "animation": {} }"#);
+        assert!(animation.is_some());
+
+        vec![
+            BlockTile {
+                id: BlockTextureId::Stone,
+                image: synthetic(1, 16, 1),
+                animation: None,
+            },
+            BlockTile {
+                id: BlockTextureId::Dirt,
+                image: synthetic(2, 32, 1),
+                animation: None,
+            },
+            BlockTile {
+                id: BlockTextureId::Water,
+                image: synthetic(3, 16, 3),
+                animation,
+            },
+        ]
+    }
+
+    fn atlas_texel(image: &Image, tile: u32, tiles_per_row: u32, x: u32, y: u32) -> [u8; 4] {
+        let tile_size = image.width() / tiles_per_row;
+        let px = (tile % tiles_per_row) * tile_size + x;
+        let py = (tile / tiles_per_row) * tile_size + y;
+        let offset = ((py * image.width() + px) * 4) as usize;
+
+        image.data.as_ref().unwrap()[offset..offset + 4]
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn largest_resolution_scales_small_tiles_up() {
+        let tiles = tiles();
+        let tile_size = TileResolution::Largest.tile_size(tiles.iter().map(|t| t.image.width()));
+        assert_eq!(tile_size, 32);
+
+        let (image, tiles_per_row, indices, animations) = build_atlas(&tiles, tile_size);
+
+        assert_eq!(tiles_per_row, 2);
+        assert_eq!((image.width(), image.height()), (64, 64));
+        assert_eq!(indices[&BlockTextureId::Dirt], 1);
+
+        for y in 0..tile_size {
+            for x in 0..tile_size {
+                // every source texel covers 2x2 atlas texels
+                let stone = atlas_texel(&image, 0, tiles_per_row, x, y);
+                assert_eq!(stone, texel(1, 0, x / 2, y / 2));
+
+                let dirt = atlas_texel(&image, 1, tiles_per_row, x, y);
+                assert_eq!(dirt, texel(2, 0, x, y));
+
+                // animations start out on their first frame
+                let water = atlas_texel(&image, 2, tiles_per_row, x, y);
+                assert_eq!(water, texel(3, 0, x / 2, y / 2));
+            }
+        }
+
+        assert_eq!(animations.len(), 1);
+        let (_, strip, slot) = &animations[0];
+        assert_eq!(slot.tile_size(), 32);
+        assert_eq!(strip.len(), 3 * frame_len(32));
+
+        for (frame, pixels) in strip.chunks_exact(frame_len(32)).enumerate() {
+            assert_eq!(pixels[..4], texel(3, frame as u32, 0, 0));
+            assert_eq!(pixels[pixels.len() - 4..], texel(3, frame as u32, 15, 15));
+        }
+    }
+
+    #[test]
+    fn fixed_resolution_scales_big_tiles_down() {
+        let tiles = tiles();
+        let tile_size = TileResolution::Fixed(16).tile_size(tiles.iter().map(|t| t.image.width()));
+        assert_eq!(tile_size, 16);
+
+        let (image, tiles_per_row, _, animations) = build_atlas(&tiles, tile_size);
+
+        assert_eq!(tiles_per_row, 2);
+        assert_eq!((image.width(), image.height()), (32, 32));
+
+        for y in 0..tile_size {
+            for x in 0..tile_size {
+                let stone = atlas_texel(&image, 0, tiles_per_row, x, y);
+                assert_eq!(stone, texel(1, 0, x, y));
+
+                // every other source texel is dropped
+                let dirt = atlas_texel(&image, 1, tiles_per_row, x, y);
+                assert_eq!(dirt, texel(2, 0, x * 2, y * 2));
+            }
+        }
+
+        assert_eq!(animations.len(), 1);
+        let (_, strip, _) = &animations[0];
+        assert_eq!(strip.len(), 3 * frame_len(16));
     }
 }
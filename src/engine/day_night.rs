@@ -0,0 +1,279 @@
+use std::f32::consts::TAU;
+
+use bevy::color::{LinearRgba, Mix, Srgba};
+use bevy::prelude::*;
+
+use super::atlas::{BlockMaterial, ChunkMaterial, TranslucentChunkMaterial};
+
+const SUN_ILLUMINANCE: f32 = 40_000.0;
+const MOON_ILLUMINANCE: f32 = 1_500.0;
+const MOON_COLOR: Color = Color::srgb(0.65, 0.72, 1.0);
+
+// sun and moon lean this far north of straight overhead, a light looking straight down
+// would have no sensible up vector
+const ORBIT_TILT: f32 = 0.35;
+
+// what the sky looks like at a few times of day, everything in between is interpolated
+struct SkyKeyframe {
+    time: f32,
+    color: Srgba,
+    // brightness of the camera's ambient light
+    ambient: f32,
+    // how much of the sky light reaches the chunks, see ChunkTextures
+    daylight: f32,
+}
+
+const NIGHT_SKY: Srgba = Srgba::rgb(0.02, 0.03, 0.08);
+const TWILIGHT_SKY: Srgba = Srgba::rgb(0.93, 0.55, 0.35);
+const DAY_SKY: Srgba = Srgba::rgb(0.52, 0.80, 0.92);
+
+const NIGHT_AMBIENT: f32 = 300.0;
+const DAY_AMBIENT: f32 = 3000.0;
+
+const NIGHT_DAYLIGHT: f32 = 0.2;
+
+const SKY_KEYFRAMES: [SkyKeyframe; 8] = [
+    SkyKeyframe {
+        time: 0.0,
+        color: NIGHT_SKY,
+        ambient: NIGHT_AMBIENT,
+        daylight: NIGHT_DAYLIGHT,
+    },
+    // dawn
+    SkyKeyframe {
+        time: 0.21,
+        color: NIGHT_SKY,
+        ambient: NIGHT_AMBIENT,
+        daylight: NIGHT_DAYLIGHT,
+    },
+    SkyKeyframe {
+        time: 0.25,
+        color: TWILIGHT_SKY,
+        ambient: 1200.0,
+        daylight: 0.6,
+    },
+    SkyKeyframe {
+        time: 0.3,
+        color: DAY_SKY,
+        ambient: DAY_AMBIENT,
+        daylight: 1.0,
+    },
+    // dusk
+    SkyKeyframe {
+        time: 0.7,
+        color: DAY_SKY,
+        ambient: DAY_AMBIENT,
+        daylight: 1.0,
+    },
+    SkyKeyframe {
+        time: 0.75,
+        color: TWILIGHT_SKY,
+        ambient: 1200.0,
+        daylight: 0.6,
+    },
+    SkyKeyframe {
+        time: 0.79,
+        color: NIGHT_SKY,
+        ambient: NIGHT_AMBIENT,
+        daylight: NIGHT_DAYLIGHT,
+    },
+    SkyKeyframe {
+        time: 1.0,
+        color: NIGHT_SKY,
+        ambient: NIGHT_AMBIENT,
+        daylight: NIGHT_DAYLIGHT,
+    },
+];
+
+pub struct DayNightPlugin {
+    // real seconds a whole day takes
+    pub day_length: f32,
+    pub start_time: f32,
+}
+
+// time of day, 0 is midnight, 0.25 sunrise, 0.5 noon and 0.75 sunset
+#[derive(Resource, Debug, Clone)]
+pub struct WorldTime {
+    time_of_day: f32,
+    pub day_length: f32,
+    // stops the clock, set_time_of_day still works
+    pub frozen: bool,
+}
+
+#[derive(Component)]
+pub struct Sun;
+
+#[derive(Component)]
+pub struct Moon;
+
+type CelestialLight<'a> = (&'a mut Transform, &'a mut DirectionalLight);
+
+impl Default for DayNightPlugin {
+    fn default() -> Self {
+        Self {
+            day_length: 1200.0,
+            start_time: 0.3,
+        }
+    }
+}
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WorldTime::new(self.start_time, self.day_length))
+            .add_systems(Startup, spawn_sun_and_moon)
+            .add_systems(
+                Update,
+                (
+                    advance_time,
+                    (move_sun_and_moon, update_sky, update_daylight),
+                )
+                    .chain(),
+            );
+    }
+}
+
+impl WorldTime {
+    pub fn new(time_of_day: f32, day_length: f32) -> WorldTime {
+        WorldTime {
+            time_of_day: time_of_day.rem_euclid(1.0),
+            day_length,
+            frozen: false,
+        }
+    }
+
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = time_of_day.rem_euclid(1.0);
+    }
+
+    // 1 with the sun straight overhead, -1 at midnight
+    pub fn sun_height(&self) -> f32 {
+        ((self.time_of_day - 0.25) * TAU).sin()
+    }
+
+    // where the sun is seen from, rises in +x and sets in -x
+    fn sun_direction(&self) -> Vec3 {
+        let angle = (self.time_of_day - 0.25) * TAU;
+        Vec3::new(angle.cos(), angle.sin(), ORBIT_TILT).normalize()
+    }
+
+    // how much of the sky light reaches the chunks right now
+    pub fn daylight(&self) -> f32 {
+        self.sky().2
+    }
+
+    fn sky(&self) -> (Srgba, f32, f32) {
+        let next = SKY_KEYFRAMES
+            .iter()
+            .position(|key| key.time > self.time_of_day)
+            .unwrap_or(SKY_KEYFRAMES.len() - 1);
+
+        let from = &SKY_KEYFRAMES[next - 1];
+        let to = &SKY_KEYFRAMES[next];
+        let t = ((self.time_of_day - from.time) / (to.time - from.time)).clamp(0.0, 1.0);
+
+        // blended in linear space, srgb would darken the middle
+        let color = LinearRgba::from(from.color).mix(&LinearRgba::from(to.color), t);
+
+        (
+            color.into(),
+            from.ambient.lerp(to.ambient, t),
+            from.daylight.lerp(to.daylight, t),
+        )
+    }
+}
+
+// 0 below `low`, 1 above `high`, eased in between
+fn smoothstep(low: f32, high: f32, x: f32) -> f32 {
+    let t = ((x - low) / (high - low)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn spawn_sun_and_moon(mut commands: Commands) {
+    commands.spawn((
+        Sun,
+        DirectionalLight {
+            illuminance: SUN_ILLUMINANCE,
+            ..default()
+        },
+        Transform::default(),
+    ));
+
+    commands.spawn((
+        Moon,
+        DirectionalLight {
+            illuminance: 0.0,
+            color: MOON_COLOR,
+            ..default()
+        },
+        Transform::default(),
+    ));
+}
+
+fn advance_time(time: Res<Time>, mut world_time: ResMut<WorldTime>) {
+    if world_time.frozen || world_time.day_length <= 0.0 {
+        return;
+    }
+
+    let time_of_day = world_time.time_of_day + time.delta_secs() / world_time.day_length;
+    world_time.set_time_of_day(time_of_day);
+}
+
+// both fade out as they sink below the horizon
+fn move_sun_and_moon(
+    world_time: Res<WorldTime>,
+    mut sun: Single<CelestialLight, (With<Sun>, Without<Moon>)>,
+    mut moon: Single<CelestialLight, (With<Moon>, Without<Sun>)>,
+) {
+    let direction = world_time.sun_direction();
+    let height = world_time.sun_height();
+
+    *sun.0 = Transform::default().looking_to(-direction, Vec3::Z);
+    sun.1.illuminance = SUN_ILLUMINANCE * smoothstep(-0.05, 0.15, height);
+
+    *moon.0 = Transform::default().looking_to(direction, Vec3::Z);
+    moon.1.illuminance = MOON_ILLUMINANCE * smoothstep(-0.05, 0.15, -height);
+}
+
+fn update_sky(
+    world_time: Res<WorldTime>,
+    mut clear_color: ResMut<ClearColor>,
+    mut ambient: Query<&mut AmbientLight, With<Camera3d>>,
+) {
+    let (color, brightness, _) = world_time.sky();
+
+    clear_color.0 = color.into();
+
+    for mut ambient in ambient.iter_mut() {
+        ambient.brightness = brightness;
+    }
+}
+
+// scales the sky light baked into the chunk meshes
+fn update_daylight(
+    world_time: Res<WorldTime>,
+    chunk_material: Option<Res<ChunkMaterial>>,
+    translucent_material: Option<Res<TranslucentChunkMaterial>>,
+    mut materials: ResMut<Assets<BlockMaterial>>,
+) {
+    let daylight = world_time.daylight();
+
+    let handles = [
+        chunk_material.map(|material| material.0.clone()),
+        translucent_material.map(|material| material.0.clone()),
+    ];
+
+    for handle in handles.into_iter().flatten() {
+        // only touch the asset when it changes, every change uploads it again
+        if materials
+            .get(&handle)
+            .is_some_and(|material| material.extension.daylight != daylight)
+            && let Some(material) = materials.get_mut(&handle)
+        {
+            material.extension.daylight = daylight;
+        }
+    }
+}
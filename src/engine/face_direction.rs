@@ -74,6 +74,15 @@ pub const DIRECTIONS: [FaceDirection; 6] = [
 ];
 
 impl FaceDirection {
+    // 0 for x, 1 for y, 2 for z
+    pub const fn axis(self) -> usize {
+        match self {
+            Self::Right | Self::Left => 0,
+            Self::Top | Self::Bottom => 1,
+            Self::Front | Self::Back => 2,
+        }
+    }
+
     pub const fn normal(self) -> IVec3 {
         match self {
             Self::Right => IVec3::X,
@@ -14,34 +14,52 @@ use super::face_direction::FaceDirection;
 struct FaceLut {
     verts: [[f32; 3]; 4],
     normal: [f32; 3],
+    // axes the u and v texture coordinates run along
+    u_axis: usize,
+    v_axis: usize,
+}
+
+// brightness per ambient occlusion level, 0 being a fully enclosed corner
+const AO_CURVE: [f32; 4] = [0.45, 0.65, 0.82, 1.0];
+
+// per corner color multiplier for faces that don't get tinted
+pub const NO_TINT: [[f32; 3]; 4] = [[1.0; 3]; 4];
+
+// overlays sit this far in front of their face so the two don't z-fight
+const OVERLAY_LIFT: f32 = 1.0 / 256.0;
+
+// uvs of a single block face, scaled by the quad size so the texture repeats per block
+const LOCAL_UVS: [[f32; 2]; 4] = [[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]];
+
+const fn differing_axis(a: [f32; 3], b: [f32; 3]) -> usize {
+    if a[0] != b[0] {
+        0
+    } else if a[1] != b[1] {
+        1
+    } else {
+        2
+    }
+}
+
+const fn face_lut(dir: usize) -> FaceLut {
+    let verts = FACE_VERTICES[dir];
+
+    FaceLut {
+        verts,
+        normal: FACE_NORMALS[dir],
+        u_axis: differing_axis(verts[0], verts[1]),
+        v_axis: differing_axis(verts[1], verts[2]),
+    }
 }
 
 // Build a single LUT to avoid multiple array lookups and bounds checks in hot code.
 const FACE_LUT: [FaceLut; 6] = [
-    FaceLut {
-        verts: FACE_VERTICES[0],
-        normal: FACE_NORMALS[0],
-    },
-    FaceLut {
-        verts: FACE_VERTICES[1],
-        normal: FACE_NORMALS[1],
-    },
-    FaceLut {
-        verts: FACE_VERTICES[2],
-        normal: FACE_NORMALS[2],
-    },
-    FaceLut {
-        verts: FACE_VERTICES[3],
-        normal: FACE_NORMALS[3],
-    },
-    FaceLut {
-        verts: FACE_VERTICES[4],
-        normal: FACE_NORMALS[4],
-    },
-    FaceLut {
-        verts: FACE_VERTICES[5],
-        normal: FACE_NORMALS[5],
-    },
+    face_lut(0),
+    face_lut(1),
+    face_lut(2),
+    face_lut(3),
+    face_lut(4),
+    face_lut(5),
 ];
 
 #[derive(Default)]
@@ -49,6 +67,9 @@ pub struct MeshBuilder {
     positions: Vec<[f32; 3]>,
     normals: Vec<[f32; 3]>,
     uvs: Vec<[f32; 2]>,
+    // atlas origin of the tile, the chunk shader wraps uvs into it
+    tiles: Vec<[f32; 2]>,
+    colors: Vec<[f32; 4]>,
     indices: Vec<u32>,
 }
 
@@ -61,68 +82,139 @@ impl MeshBuilder {
             positions: Vec::with_capacity(v),
             normals: Vec::with_capacity(v),
             uvs: Vec::with_capacity(v),
+            tiles: Vec::with_capacity(v),
+            colors: Vec::with_capacity(v),
             indices: Vec::with_capacity(i),
         }
     }
 
     #[inline(always)]
-    pub fn add_face(&mut self, dir: FaceDirection, pos: IVec3, face_uvs: [[f32; 2]; 4]) {
-        let base = self.positions.len() as u32;
+    pub fn add_face(
+        &mut self,
+        dir: FaceDirection,
+        pos: IVec3,
+        tile: [f32; 2],
+        ao: [u8; 4],
+        color: [[f32; 4]; 4],
+    ) {
+        self.add_quad(dir, pos, pos, tile, ao, color);
+    }
 
-        let px = pos.x as f32;
-        let py = pos.y as f32;
-        let pz = pos.z as f32;
+    // one quad covering the `dir` faces of every block between `min` and `max` (inclusive).
+    // both must lie in the same plane. `ao` is the occlusion level of each corner, see AO_CURVE.
+    // `color` per corner, rgb multiplies the texture and alpha is the block light share,
+    // see vertex_light
+    #[inline(always)]
+    pub fn add_quad(
+        &mut self,
+        dir: FaceDirection,
+        min: IVec3,
+        max: IVec3,
+        tile: [f32; 2],
+        ao: [u8; 4],
+        color: [[f32; 4]; 4],
+    ) {
+        let base = self.positions.len() as u32;
 
         let face = unsafe { FACE_LUT.get_unchecked(dir as usize) };
-        let v = &face.verts;
-        let n = face.normal;
-
-        self.positions.reserve(4);
-        self.normals.reserve(4);
-        self.uvs.reserve(4);
-        self.indices.reserve(6);
-
-        unsafe {
-            // ---- positions (write 4) ----
-            let p_len = self.positions.len();
-            let p_ptr = self.positions.as_mut_ptr().add(p_len);
-            self.positions.set_len(p_len + 4);
-            *p_ptr.add(0) = [v[0][0] + px, v[0][1] + py, v[0][2] + pz];
-            *p_ptr.add(1) = [v[1][0] + px, v[1][1] + py, v[1][2] + pz];
-            *p_ptr.add(2) = [v[2][0] + px, v[2][1] + py, v[2][2] + pz];
-            *p_ptr.add(3) = [v[3][0] + px, v[3][1] + py, v[3][2] + pz];
-
-            // ---- normals (write 4) ----
-            let n_len = self.normals.len();
-            let n_ptr = self.normals.as_mut_ptr().add(n_len);
-            self.normals.set_len(n_len + 4);
-            *n_ptr.add(0) = n;
-            *n_ptr.add(1) = n;
-            *n_ptr.add(2) = n;
-            *n_ptr.add(3) = n;
-
-            // ---- uvs (write 4) ----
-            let uv_len = self.uvs.len();
-            let uv_ptr = self.uvs.as_mut_ptr().add(uv_len);
-            self.uvs.set_len(uv_len + 4);
-            *uv_ptr.add(0) = face_uvs[0];
-            *uv_ptr.add(1) = face_uvs[1];
-            *uv_ptr.add(2) = face_uvs[2];
-            *uv_ptr.add(3) = face_uvs[3];
-
-            // ---- indices (write 6) ----
-            let i_len = self.indices.len();
-            let i_ptr = self.indices.as_mut_ptr().add(i_len);
-            self.indices.set_len(i_len + 6);
-            *i_ptr.add(0) = base;
-            *i_ptr.add(1) = base + 1;
-            *i_ptr.add(2) = base + 2;
-            *i_ptr.add(3) = base;
-            *i_ptr.add(4) = base + 2;
-            *i_ptr.add(5) = base + 3;
+        let size = (max - min + IVec3::ONE).to_array();
+
+        let min = min.as_vec3().to_array();
+        let max = max.as_vec3().to_array();
+
+        for (((corner, local_uv), ao), color) in face.verts.iter().zip(LOCAL_UVS).zip(ao).zip(color)
+        {
+            let mut position = [0.0; 3];
+            for axis in 0..3 {
+                position[axis] = if corner[axis] < 0.0 {
+                    min[axis] + corner[axis]
+                } else {
+                    max[axis] + corner[axis]
+                };
+            }
+
+            self.positions.push(position);
+            self.normals.push(face.normal);
+            self.uvs.push([
+                local_uv[0] * size[face.u_axis] as f32,
+                local_uv[1] * size[face.v_axis] as f32,
+            ]);
+            self.tiles.push(tile);
+
+            let brightness = AO_CURVE[ao as usize];
+            self.colors.push([
+                color[0] * brightness,
+                color[1] * brightness,
+                color[2] * brightness,
+                color[3],
+            ]);
+        }
+
+        // split along the brighter diagonal, otherwise a single dark corner
+        // bleeds across the whole quad
+        if (ao[0] as u32 + ao[2] as u32) < ao[1] as u32 + ao[3] as u32 {
+            self.indices.extend_from_slice(&[
+                base,
+                base + 1,
+                base + 3,
+                base + 1,
+                base + 2,
+                base + 3,
+            ]);
+        } else {
+            self.indices
+                .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+    }
+
+    // same as add_quad, drawn just in front of it. for textures layered over a face
+    pub fn add_overlay_quad(
+        &mut self,
+        dir: FaceDirection,
+        min: IVec3,
+        max: IVec3,
+        tile: [f32; 2],
+        ao: [u8; 4],
+        color: [[f32; 4]; 4],
+    ) {
+        let base = self.positions.len();
+        self.add_quad(dir, min, max, tile, ao, color);
+
+        let normal = FACE_NORMALS[dir as usize];
+        for position in &mut self.positions[base..] {
+            for axis in 0..3 {
+                position[axis] += normal[axis] * OVERLAY_LIFT;
+            }
         }
     }
 
+    // a single block sized quad with arbitrary corners, for shapes that aren't full cubes.
+    // corners go around the same way as FACE_VERTICES and take the same uvs
+    pub fn add_shaped_quad(
+        &mut self,
+        corners: [[f32; 3]; 4],
+        normal: [f32; 3],
+        tile: [f32; 2],
+        color: [f32; 4],
+    ) {
+        let base = self.positions.len() as u32;
+
+        for (corner, local_uv) in corners.into_iter().zip(LOCAL_UVS) {
+            self.positions.push(corner);
+            self.normals.push(normal);
+            self.uvs.push(local_uv);
+            self.tiles.push(tile);
+            self.colors.push(color);
+        }
+
+        self.indices
+            .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
     #[inline]
     pub fn build_mesh(self) -> Mesh {
         let mut mesh = Mesh::new(
@@ -133,6 +225,8 @@ impl MeshBuilder {
         mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
         mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
         mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_1, self.tiles);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
         mesh.insert_indices(Indices::U32(self.indices));
 
         mesh
@@ -1,5 +1,8 @@
 pub mod atlas;
 pub mod camera;
+pub mod day_night;
 mod face_direction;
 mod mesh_builder;
+pub mod resource_pack;
+mod texture_animation;
 pub mod world;
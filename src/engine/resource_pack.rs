@@ -0,0 +1,253 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::asset::io::file::FileAssetReader;
+use bevy::image::{CompressedImageFormats, ImageSampler, ImageType};
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use serde::Deserialize;
+use zip::ZipArchive;
+
+use super::atlas::TILE_SIZE;
+use super::texture_animation::AnimationInfo;
+
+// the textures that ship with the game, always searched last
+const BUILTIN_PACK_DIR: &str = "minecraft_assets";
+
+// where minecraft resource packs keep the files we read
+const PACK_ASSET_ROOT: &str = "assets/minecraft";
+
+// the parts of a pack.mcmeta we care about
+#[derive(Deserialize)]
+struct PackMcMeta {
+    pack: PackMeta,
+}
+
+#[derive(Deserialize)]
+struct PackMeta {
+    // plain text or a text component, only the plain kind gets logged
+    #[serde(default)]
+    description: serde_json::Value,
+}
+
+enum PackSource {
+    Directory(PathBuf),
+    Zip(Mutex<ZipArchive<File>>),
+}
+
+pub struct ResourcePack {
+    name: String,
+    source: PackSource,
+    // prefix of every asset path inside the pack
+    root: &'static str,
+}
+
+// packs in priority order, the first one that has a usable texture wins.
+// the built-in pack is always last so every texture has something to fall back to
+#[derive(Resource)]
+pub struct ResourcePacks(Vec<ResourcePack>);
+
+// a block texture and its animation, if its pack has a .mcmeta next to it
+pub struct PackTexture {
+    pub image: Image,
+    pub animation: Option<AnimationInfo>,
+}
+
+impl ResourcePack {
+    fn builtin() -> ResourcePack {
+        ResourcePack {
+            name: String::from("built-in"),
+            source: PackSource::Directory(
+                FileAssetReader::get_base_path()
+                    .join("assets")
+                    .join(BUILTIN_PACK_DIR),
+            ),
+            root: "",
+        }
+    }
+
+    // a pack directory or zip file. None if it can't be read or has no valid pack.mcmeta
+    pub fn open(path: &Path) -> Option<ResourcePack> {
+        let name = path.display().to_string();
+
+        let source = if path.is_dir() {
+            PackSource::Directory(path.to_path_buf())
+        } else {
+            let archive = File::open(path)
+                .ok()
+                .and_then(|file| ZipArchive::new(file).ok());
+
+            match archive {
+                Some(archive) => PackSource::Zip(Mutex::new(archive)),
+                None => {
+                    warn!(
+                        "resource pack {name} is neither a directory nor a zip file, skipping it"
+                    );
+                    return None;
+                }
+            }
+        };
+
+        let pack = ResourcePack {
+            name,
+            source,
+            root: PACK_ASSET_ROOT,
+        };
+
+        let Some(meta) = pack.read_file("pack.mcmeta") else {
+            warn!(
+                "resource pack {} has no pack.mcmeta, skipping it",
+                pack.name
+            );
+            return None;
+        };
+
+        match serde_json::from_slice::<PackMcMeta>(&meta) {
+            Ok(meta) => match meta.pack.description.as_str() {
+                Some(description) => info!("resource pack {}: {description}", pack.name),
+                None => info!("resource pack {}", pack.name),
+            },
+            Err(err) => {
+                warn!(
+                    "resource pack {} has an invalid pack.mcmeta: {err}",
+                    pack.name
+                );
+                return None;
+            }
+        }
+
+        Some(pack)
+    }
+
+    // `path` is relative to the pack root
+    fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+        match &self.source {
+            PackSource::Directory(dir) => std::fs::read(dir.join(path)).ok(),
+            PackSource::Zip(archive) => {
+                let mut archive = archive.lock().unwrap();
+                let mut file = archive.by_name(path).ok()?;
+
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes).ok()?;
+
+                Some(bytes)
+            }
+        }
+    }
+
+    // `path` is relative to the pack's assets, like textures/block/stone.png
+    pub fn read(&self, path: &str) -> Option<Vec<u8>> {
+        if self.root.is_empty() {
+            self.read_file(path)
+        } else {
+            self.read_file(&format!("{}/{path}", self.root))
+        }
+    }
+
+    fn load_image(&self, path: &str) -> Option<Image> {
+        let bytes = self.read(path)?;
+
+        match Image::from_buffer(
+            &bytes,
+            ImageType::Extension("png"),
+            CompressedImageFormats::NONE,
+            true,
+            ImageSampler::Default,
+            RenderAssetUsages::MAIN_WORLD,
+        ) {
+            Ok(image) => Some(image),
+            Err(err) => {
+                warn!("{}: can't decode {path}: {err}", self.name);
+                None
+            }
+        }
+    }
+
+    fn load_texture(&self, path: &str) -> Option<PackTexture> {
+        let image = self.load_image(path)?;
+
+        if let Err(reason) = validate_texture(&image) {
+            warn!("{}: {path} {reason}", self.name);
+            return None;
+        }
+
+        let animation = self
+            .read(&format!("{path}.mcmeta"))
+            .and_then(|meta| AnimationInfo::parse(&String::from_utf8_lossy(&meta)));
+
+        Some(PackTexture { image, animation })
+    }
+}
+
+impl Default for ResourcePacks {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl ResourcePacks {
+    // packs that fail to open are skipped
+    pub fn new(paths: Vec<PathBuf>) -> ResourcePacks {
+        let mut packs: Vec<ResourcePack> = paths
+            .iter()
+            .filter_map(|path| ResourcePack::open(path))
+            .collect();
+
+        packs.push(ResourcePack::builtin());
+
+        ResourcePacks(packs)
+    }
+
+    // every `--resource-pack <path>` or `--resource-pack=<path>`, earlier ones take priority
+    pub fn from_args() -> ResourcePacks {
+        let mut args = std::env::args().skip(1);
+        let mut paths = Vec::new();
+
+        while let Some(arg) = args.next() {
+            if let Some(value) = arg.strip_prefix("--resource-pack=") {
+                paths.push(PathBuf::from(value));
+            } else if arg == "--resource-pack"
+                && let Some(value) = args.next()
+            {
+                paths.push(PathBuf::from(value));
+            }
+        }
+
+        Self::new(paths)
+    }
+
+    // any png, from the first pack that has one that decodes
+    pub fn load_image(&self, path: &str) -> Option<Image> {
+        self.0.iter().find_map(|pack| pack.load_image(path))
+    }
+
+    // the texture from the first pack that has a usable one
+    pub fn load_texture(&self, path: &str) -> Option<PackTexture> {
+        self.0.iter().find_map(|pack| pack.load_texture(path))
+    }
+}
+
+// square frames, TILE_SIZE or a multiple of it wide. animations stack their frames vertically
+fn validate_texture(image: &Image) -> Result<(), String> {
+    let (width, height) = (image.width(), image.height());
+
+    if image.texture_descriptor.format != TextureFormat::Rgba8UnormSrgb {
+        return Err(format!(
+            "has an unsupported pixel format {:?}",
+            image.texture_descriptor.format
+        ));
+    }
+
+    if width == 0 || width % TILE_SIZE != 0 {
+        return Err(format!("is {width} wide, not a multiple of {TILE_SIZE}"));
+    }
+
+    if height == 0 || height % width != 0 {
+        return Err(format!("is {width}x{height}, frames have to be square"));
+    }
+
+    Ok(())
+}
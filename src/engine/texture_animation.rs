@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::atlas::{TileSlot, write_tile};
+
+// minecraft counts animation time in game ticks
+const TICKS_PER_SECOND: f32 = 20.0;
+
+// the parts of a .mcmeta file we care about
+#[derive(Deserialize)]
+struct McMeta {
+    animation: Option<AnimationMeta>,
+}
+
+#[derive(Deserialize)]
+struct AnimationMeta {
+    #[serde(default = "default_frametime")]
+    frametime: u32,
+    #[serde(default)]
+    interpolate: bool,
+    frames: Option<Vec<FrameMeta>>,
+}
+
+// frames are either a bare index or an index with its own duration
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrameMeta {
+    Index(usize),
+    Timed { index: usize, time: u32 },
+}
+
+fn default_frametime() -> u32 {
+    1
+}
+
+// how a texture strip animates, read from the .mcmeta next to it
+#[derive(Clone)]
+pub struct AnimationInfo {
+    frametime: u32,
+    // (frame in the strip, ticks it stays up). None plays every frame once, top to bottom
+    frames: Option<Vec<(usize, u32)>>,
+    interpolate: bool,
+}
+
+impl AnimationInfo {
+    // None if the mcmeta doesn't describe an animation
+    pub fn parse(text: &str) -> Option<AnimationInfo> {
+        let meta: McMeta = match serde_json::from_str(text) {
+            Ok(meta) => meta,
+            Err(err) => {
+                warn!("invalid mcmeta: {err}");
+                return None;
+            }
+        };
+
+        let animation = meta.animation?;
+        let frametime = animation.frametime.max(1);
+
+        let frames = animation.frames.map(|frames| {
+            frames
+                .into_iter()
+                .map(|frame| match frame {
+                    FrameMeta::Index(index) => (index, frametime),
+                    FrameMeta::Timed { index, time } => (index, time.max(1)),
+                })
+                .collect()
+        });
+
+        Some(AnimationInfo {
+            frametime,
+            frames,
+            interpolate: animation.interpolate,
+        })
+    }
+}
+
+struct AnimatedTile {
+    slot: TileSlot,
+    // every frame of the strip, tile_size * tile_size rgba each
+    frames: Vec<Vec<u8>>,
+    sequence: Vec<(usize, u32)>,
+    interpolate: bool,
+    total_ticks: u32,
+    // what was written last, so unchanged frames don't reupload the texture
+    shown: Option<(usize, usize, u32)>,
+}
+
+#[derive(Resource)]
+pub struct AnimatedTiles {
+    texture: Handle<Image>,
+    tiles: Vec<AnimatedTile>,
+}
+
+impl AnimatedTiles {
+    pub fn new(texture: Handle<Image>) -> AnimatedTiles {
+        AnimatedTiles {
+            texture,
+            tiles: Vec::new(),
+        }
+    }
+
+    // `strip` holds the frames stacked vertically, already at the slot's tile size
+    pub fn add(&mut self, info: AnimationInfo, strip: &[u8], slot: TileSlot) {
+        let frame_len = (slot.tile_size() * slot.tile_size() * 4) as usize;
+        let frames: Vec<Vec<u8>> = strip.chunks_exact(frame_len).map(<[u8]>::to_vec).collect();
+
+        let sequence: Vec<(usize, u32)> = match info.frames {
+            Some(sequence) => sequence
+                .into_iter()
+                .filter(|(index, _)| *index < frames.len())
+                .collect(),
+            None => (0..frames.len()).map(|i| (i, info.frametime)).collect(),
+        };
+
+        if sequence.len() < 2 {
+            return;
+        }
+
+        self.tiles.push(AnimatedTile {
+            slot,
+            frames,
+            total_ticks: sequence.iter().map(|(_, ticks)| ticks).sum(),
+            sequence,
+            interpolate: info.interpolate,
+            shown: None,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+}
+
+impl AnimatedTile {
+    // (current frame, next frame, blend towards next in 0..=255)
+    fn state_at(&self, ticks: f32) -> (usize, usize, u32) {
+        let mut t = ticks % self.total_ticks as f32;
+
+        for (i, (frame, duration)) in self.sequence.iter().enumerate() {
+            if t < *duration as f32 {
+                let next = self.sequence[(i + 1) % self.sequence.len()].0;
+                let blend = if self.interpolate {
+                    (t / *duration as f32 * 255.0) as u32
+                } else {
+                    0
+                };
+
+                return (*frame, next, blend);
+            }
+
+            t -= *duration as f32;
+        }
+
+        let (frame, _) = self.sequence[0];
+        (frame, frame, 0)
+    }
+}
+
+pub fn animate_tiles(
+    time: Res<Time>,
+    mut animated: ResMut<AnimatedTiles>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let ticks = time.elapsed_secs() * TICKS_PER_SECOND;
+
+    let AnimatedTiles { texture, tiles } = &mut *animated;
+
+    let mut texture_image = None;
+
+    for tile in tiles.iter_mut() {
+        let state = tile.state_at(ticks);
+        if tile.shown == Some(state) {
+            continue;
+        }
+        tile.shown = Some(state);
+
+        // only borrow the texture mutably once something changed, that's what triggers the upload
+        if texture_image.is_none() {
+            texture_image = images.get_mut(texture.id());
+        }
+        let Some(data) = texture_image.as_mut().and_then(|image| image.data.as_mut()) else {
+            return;
+        };
+
+        let (frame, next, blend) = state;
+
+        if blend == 0 {
+            write_tile(data, tile.slot, &tile.frames[frame]);
+            continue;
+        }
+
+        let mixed: Vec<u8> = tile.frames[frame]
+            .iter()
+            .zip(&tile.frames[next])
+            .map(|(a, b)| ((*a as u32 * (255 - blend) + *b as u32 * blend) / 255) as u8)
+            .collect();
+
+        write_tile(data, tile.slot, &mixed);
+    }
+}
@@ -1,11 +1,15 @@
+use bevy::math::IVec3;
+
 use crate::engine::world::block::BlockType;
-use crate::engine::world::structure::StructureRule;
+use crate::engine::world::chunk::WATER_HEIGHT;
+use crate::engine::world::structure::{StructureRule, hash_2d};
 
 use super::biomes::desert::Desert;
 use super::biomes::jungle::Jungle;
 use super::biomes::plains::Plains;
 use super::biomes::tundra::Tundra;
 use super::climate_sampler::ClimateSample;
+use super::seed::WorldSeed;
 
 #[derive(Copy, Clone)]
 pub struct SurfaceRules {
@@ -16,10 +20,45 @@ pub struct SurfaceRules {
     pub moist_weight: f64,
 }
 
-pub trait Biome {
+// what a column is made of, counted down from every block that is exposed from above
+#[derive(Copy, Clone)]
+pub struct SurfaceLayers {
+    pub top: BlockType,
+    // (block, thickness) from top to bottom, stone/deepslate follows after
+    pub filler: &'static [(BlockType, i32)],
+}
+
+// river valleys get these instead of the biome's own layers
+pub const RIVER_BANK_LAYERS: SurfaceLayers = SurfaceLayers {
+    top: BlockType::Sand,
+    filler: &[(BlockType::Gravel, 2), (BlockType::Sand, 2)],
+};
+// how much of a river a column has to be to get bank layers
+pub const RIVER_BANK_THRESHOLD: f64 = 0.35;
+// rivers pull the terrain down to this height, a few blocks under the water line
+const RIVER_BED_HEIGHT: f64 = WATER_HEIGHT as f64 - 4.0;
+
+// everything at or below this is deepslate
+const DEEPSLATE_LEVEL: i32 = 12;
+// above DEEPSLATE_LEVEL stone and deepslate mix over this many blocks
+const DEEPSLATE_TRANSITION: i32 = 8;
+
+pub trait Biome: Send + Sync {
     fn get_surface(&self) -> SurfaceRules;
     fn height_offset(&self, x: i32, z: i32, climate: &ClimateSample) -> f64;
-    fn ground_block(&self) -> BlockType;
+
+    // added on top of the terrain density, positive values add material.
+    // only evaluated close to the blended surface, see `DENSITY_BAND` in chunk.rs
+    fn density_offset(&self, x: i32, y: i32, z: i32, climate: &ClimateSample) -> f64 {
+        0.0
+    }
+
+    fn surface_layers(&self) -> SurfaceLayers;
+
+    // the top layer of water turns to ice, rivers and lakes alike
+    fn water_freezes(&self) -> bool {
+        false
+    }
 
     fn structures(&self) -> &[StructureRule] {
         &[]
@@ -30,6 +69,52 @@ pub struct BiomeSelector {
     biomes: Vec<Box<dyn Biome>>,
 }
 
+impl SurfaceLayers {
+    // `depth` is 0 for the exposed block itself
+    pub fn block_at(&self, depth: i32, pos: IVec3, seed: u32) -> BlockType {
+        if depth == 0 {
+            // grass or snow don't belong on the sea floor
+            let underwater = pos.y < WATER_HEIGHT as i32 - 1;
+
+            return match self.filler.first() {
+                Some(&(filler, _)) if underwater => filler,
+                _ => self.top,
+            };
+        }
+
+        let mut remaining = depth - 1;
+        for &(block, thickness) in self.filler {
+            if remaining < thickness {
+                return block;
+            }
+            remaining -= thickness;
+        }
+
+        deep_block(pos, seed)
+    }
+}
+
+fn deep_block(pos: IVec3, seed: u32) -> BlockType {
+    let above = pos.y - DEEPSLATE_LEVEL;
+
+    if above <= 0 {
+        return BlockType::Deepslate;
+    }
+    if above >= DEEPSLATE_TRANSITION {
+        return BlockType::Stone;
+    }
+
+    // dithered so the boundary isn't a flat plane
+    let h = hash_2d(pos.x, pos.z, seed ^ (pos.y as u32).wrapping_mul(0x165667B1));
+    let r = (h as f64) / (u32::MAX as f64);
+
+    if r < above as f64 / DEEPSLATE_TRANSITION as f64 {
+        BlockType::Stone
+    } else {
+        BlockType::Deepslate
+    }
+}
+
 impl SurfaceRules {
     fn compute_delta(&self, sample: &ClimateSample) -> f64 {
         const EXPONENT: f64 = 4.;
@@ -62,37 +147,73 @@ impl BiomeSelector {
         best_biome.as_ref()
     }
 
-    pub fn blended_height(&self, base: i32, x: i32, z: i32, climate: &ClimateSample) -> i32 {
+    // normalized influence of every biome on a column, in registration order.
+    // whatever is left to 1.0 belongs to the unmodified base terrain
+    pub fn blend_weights(&self, climate: &ClimateSample) -> Vec<f64> {
         const SHARPNESS: f64 = 2.5;
         const EPSILON: f64 = 0.0001;
         const BASE_STRENGTH: f64 = 1.0; // tune this
 
-        let mut total_weight = BASE_STRENGTH;
-        let mut total_value = BASE_STRENGTH * base as f64;
+        let mut weights: Vec<f64> = self
+            .biomes
+            .iter()
+            .map(|biome| {
+                let delta = biome.get_surface().compute_delta(climate).max(EPSILON);
+                1.0 / delta.powf(SHARPNESS)
+            })
+            .collect();
+
+        let total_weight = BASE_STRENGTH + weights.iter().sum::<f64>();
+        for weight in &mut weights {
+            *weight /= total_weight;
+        }
+
+        weights
+    }
 
-        for biome in &self.biomes {
-            let surface = biome.get_surface();
-            let delta = surface.compute_delta(climate).max(EPSILON);
+    pub fn blended_height(&self, base: i32, x: i32, z: i32, climate: &ClimateSample) -> i32 {
+        let weights = self.blend_weights(climate);
 
-            let weight = 1.0 / delta.powf(SHARPNESS);
-            let value = base as f64 + biome.height_offset(x, z, climate) as f64;
+        let offset: f64 = self
+            .biomes
+            .iter()
+            .zip(&weights)
+            .map(|(biome, weight)| weight * biome.height_offset(x, z, climate))
+            .sum();
 
-            total_weight += weight;
-            total_value += weight * value;
-        }
+        let height = base as f64 + offset;
 
-        (total_value / total_weight) as i32
+        // lerp towards the bed so the valley walls stay continuous, never raise anything though
+        let height = height + (RIVER_BED_HEIGHT - height).min(0.0) * climate.river;
+
+        height as i32
+    }
+
+    pub fn blended_density_offset(
+        &self,
+        weights: &[f64],
+        pos: IVec3,
+        climate: &ClimateSample,
+    ) -> f64 {
+        const NEGLIGIBLE: f64 = 0.001;
+
+        self.biomes
+            .iter()
+            .zip(weights)
+            .filter(|(_, weight)| **weight > NEGLIGIBLE)
+            .map(|(biome, weight)| weight * biome.density_offset(pos.x, pos.y, pos.z, climate))
+            .sum()
     }
 }
 
-impl Default for BiomeSelector {
-    fn default() -> Self {
-        Self {
+impl BiomeSelector {
+    pub fn new(seed: &WorldSeed) -> BiomeSelector {
+        BiomeSelector {
             biomes: vec![
-                Box::new(Plains),
-                Box::new(Jungle),
-                Box::new(Desert),
-                Box::new(Tundra),
+                Box::new(Plains::new(seed)),
+                Box::new(Jungle::new(seed)),
+                Box::new(Desert::new(seed)),
+                Box::new(Tundra::new(seed)),
             ],
         }
     }
@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use bevy::color::{ColorToComponents, LinearRgba, Srgba};
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+
+use super::chunk::CHUNK_SIZE;
+use super::climate_sampler::{ClimateSample, ClimateSampler};
+use crate::engine::resource_pack::ResourcePacks;
+
+const GRASS_COLORMAP_PATH: &str = "textures/colormap/grass.png";
+const FOLIAGE_COLORMAP_PATH: &str = "textures/colormap/foliage.png";
+
+// used when no pack has a colormap, minecraft's plains colors
+const DEFAULT_GRASS: Srgba = Srgba::rgb(0.569, 0.741, 0.349);
+const DEFAULT_FOLIAGE: Srgba = Srgba::rgb(0.467, 0.671, 0.184);
+
+// spruce needles don't follow the biome
+const EVERGREEN: Srgba = Srgba::rgb(0.380, 0.600, 0.380);
+
+// columns averaged in every direction, like minecraft's biome blend
+const BLEND_RADIUS: i32 = 2;
+
+// the chunk plus one column on every side, quad corners on the edge look at those
+const TINT_SIZE: usize = CHUNK_SIZE + 2;
+
+// which color a tinted texture gets multiplied with
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BiomeTint {
+    Grass,
+    Foliage,
+    Evergreen,
+}
+
+// minecraft colormap, temperature runs right to left and humidity bottom to top
+pub struct Colormap {
+    width: u32,
+    height: u32,
+    // linear rgb, so blending and multiplying with the texture stays correct
+    pixels: Vec<Vec3>,
+}
+
+#[derive(Resource, Clone)]
+pub struct BiomeColormaps {
+    grass: Arc<Colormap>,
+    foliage: Arc<Colormap>,
+}
+
+// blended tints of the columns a chunk mesh touches
+pub struct ChunkTints {
+    grass: Vec<Vec3>,
+    foliage: Vec<Vec3>,
+}
+
+impl Colormap {
+    fn from_image(image: &Image) -> Option<Colormap> {
+        if image.texture_descriptor.format != TextureFormat::Rgba8UnormSrgb {
+            return None;
+        }
+
+        let pixels = image
+            .data
+            .as_ref()?
+            .chunks_exact(4)
+            .map(|p| {
+                let color = Srgba::rgb_u8(p[0], p[1], p[2]);
+                Vec3::from_array(LinearRgba::from(color).to_f32_array_no_alpha())
+            })
+            .collect();
+
+        Some(Colormap {
+            width: image.width(),
+            height: image.height(),
+            pixels,
+        })
+    }
+
+    fn uniform(color: Srgba) -> Colormap {
+        Colormap {
+            width: 1,
+            height: 1,
+            pixels: vec![Vec3::from_array(
+                LinearRgba::from(color).to_f32_array_no_alpha(),
+            )],
+        }
+    }
+
+    fn load(packs: &ResourcePacks, path: &str, fallback: Srgba) -> Colormap {
+        match packs
+            .load_image(path)
+            .and_then(|image| Self::from_image(&image))
+        {
+            Some(colormap) => colormap,
+            None => {
+                warn!("no usable {path}, biomes won't change its color");
+                Self::uniform(fallback)
+            }
+        }
+    }
+
+    // same lookup as minecraft, dry climates only reach the lower triangle
+    fn sample(&self, climate: &ClimateSample) -> Vec3 {
+        let temperature = climate.temperature.clamp(0.0, 1.0);
+        let humidity = climate.moisture.clamp(0.0, 1.0) * temperature;
+
+        let x = ((1.0 - temperature) * (self.width - 1) as f64) as u32;
+        let y = ((1.0 - humidity) * (self.height - 1) as f64) as u32;
+
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+pub fn load_colormaps(mut commands: Commands, packs: Res<ResourcePacks>) {
+    commands.insert_resource(BiomeColormaps {
+        grass: Arc::new(Colormap::load(&packs, GRASS_COLORMAP_PATH, DEFAULT_GRASS)),
+        foliage: Arc::new(Colormap::load(
+            &packs,
+            FOLIAGE_COLORMAP_PATH,
+            DEFAULT_FOLIAGE,
+        )),
+    });
+}
+
+impl ChunkTints {
+    pub fn new(chunk_coord: IVec2, sampler: &ClimateSampler, colormaps: &BiomeColormaps) -> Self {
+        let size = TINT_SIZE as i32 + BLEND_RADIUS * 2;
+        let origin = chunk_coord * CHUNK_SIZE as i32 - IVec2::splat(1 + BLEND_RADIUS);
+
+        let mut grass = Vec::with_capacity((size * size) as usize);
+        let mut foliage = Vec::with_capacity((size * size) as usize);
+
+        for z in 0..size {
+            for x in 0..size {
+                let climate = sampler.sample(origin.x + x, origin.y + z);
+
+                grass.push(colormaps.grass.sample(&climate));
+                foliage.push(colormaps.foliage.sample(&climate));
+            }
+        }
+
+        ChunkTints {
+            grass: blend(&grass, size as usize),
+            foliage: blend(&foliage, size as usize),
+        }
+    }
+
+    // grass and foliage both take `color` of each column, counted from the chunk's origin
+    #[cfg(test)]
+    pub fn from_columns(color: impl Fn(IVec2) -> Vec3) -> Self {
+        let mut columns = Vec::with_capacity(TINT_SIZE * TINT_SIZE);
+        for z in -1..=CHUNK_SIZE as i32 {
+            for x in -1..=CHUNK_SIZE as i32 {
+                columns.push(color(IVec2::new(x, z)));
+            }
+        }
+
+        ChunkTints {
+            grass: columns.clone(),
+            foliage: columns,
+        }
+    }
+
+    // tint of a quad corner, `corner` counts block edges from the chunk's origin
+    // so it's 0..=CHUNK_SIZE on both axes. averages the four columns meeting there
+    pub fn corner(&self, tint: BiomeTint, corner: IVec2) -> [f32; 3] {
+        let columns = match tint {
+            BiomeTint::Grass => &self.grass,
+            BiomeTint::Foliage => &self.foliage,
+            BiomeTint::Evergreen => {
+                return LinearRgba::from(EVERGREEN).to_f32_array_no_alpha();
+            }
+        };
+
+        // column x sits at index x + 1
+        let at = |x: i32, z: i32| columns[x as usize + z as usize * TINT_SIZE];
+
+        let sum = at(corner.x, corner.y)
+            + at(corner.x + 1, corner.y)
+            + at(corner.x, corner.y + 1)
+            + at(corner.x + 1, corner.y + 1);
+
+        (sum / 4.0).to_array()
+    }
+}
+
+// box blur over BLEND_RADIUS, shrinks the square from `size` to TINT_SIZE
+fn blend(colors: &[Vec3], size: usize) -> Vec<Vec3> {
+    let diameter = BLEND_RADIUS as usize * 2 + 1;
+
+    let mut rows = Vec::with_capacity(TINT_SIZE * size);
+    for z in 0..size {
+        for x in 0..TINT_SIZE {
+            let sum: Vec3 = colors[z * size + x..z * size + x + diameter].iter().sum();
+            rows.push(sum / diameter as f32);
+        }
+    }
+
+    let mut blended = Vec::with_capacity(TINT_SIZE * TINT_SIZE);
+    for z in 0..TINT_SIZE {
+        for x in 0..TINT_SIZE {
+            let sum: Vec3 = (z..z + diameter).map(|zz| rows[zz * TINT_SIZE + x]).sum();
+            blended.push(sum / diameter as f32);
+        }
+    }
+
+    blended
+}
@@ -1,13 +1,34 @@
-use noise::NoiseFn;
+use noise::{Fbm, NoiseFn, Perlin};
 
 use crate::engine::world::{
-    biome::{Biome, SurfaceRules},
-    biomes::terrain_noise::FBM_DESERT,
+    biome::{Biome, SurfaceLayers, SurfaceRules},
+    biomes::terrain_noise::fbm_desert,
     block::BlockType,
+    chunk::{CHUNK_HEIGHT, WATER_HEIGHT},
     climate_sampler::ClimateSample,
+    seed::WorldSeed,
+    structure::StructureRule,
+    structures::cactus,
 };
 
-pub struct Desert;
+pub struct Desert {
+    noise: Fbm<Perlin>,
+}
+
+impl Desert {
+    pub fn new(seed: &WorldSeed) -> Desert {
+        Desert {
+            noise: fbm_desert(seed),
+        }
+    }
+}
+
+static STRUCTURES: [StructureRule; 1] = [StructureRule {
+    rarity: 0.004,
+    min_height: WATER_HEIGHT as i32 + 2,
+    max_height: CHUNK_HEIGHT as i32 - 4,
+    generator: cactus::generate,
+}];
 
 impl Biome for Desert {
     fn get_surface(&self) -> SurfaceRules {
@@ -22,7 +43,9 @@ impl Biome for Desert {
     fn height_offset(&self, x: i32, z: i32, climate: &ClimateSample) -> f64 {
         const SCALE: f64 = 0.002; // very wide
 
-        let n = FBM_DESERT.get([x as f64 * SCALE + 2000.0, z as f64 * SCALE + 2000.0]);
+        let n = self
+            .noise
+            .get([x as f64 * SCALE + 2000.0, z as f64 * SCALE + 2000.0]);
 
         // deserts suppress terrain variation
         let heat = climate.temperature.clamp(0.0, 1.0);
@@ -31,7 +54,14 @@ impl Biome for Desert {
         n * 2.0 * strength
     }
 
-    fn ground_block(&self) -> BlockType {
-        BlockType::Sand
+    fn surface_layers(&self) -> SurfaceLayers {
+        SurfaceLayers {
+            top: BlockType::Sand,
+            filler: &[(BlockType::Sand, 3), (BlockType::Sandstone, 4)],
+        }
+    }
+
+    fn structures(&self) -> &[StructureRule] {
+        &STRUCTURES
     }
 }
@@ -1,13 +1,34 @@
-use noise::NoiseFn;
+use noise::{Fbm, NoiseFn, Perlin};
 
 use crate::engine::world::{
-    biome::{Biome, SurfaceRules},
-    biomes::terrain_noise::FBM_JUNGLE,
+    biome::{Biome, SurfaceLayers, SurfaceRules},
+    biomes::terrain_noise::fbm_jungle,
     block::BlockType,
+    chunk::{CHUNK_HEIGHT, WATER_HEIGHT},
     climate_sampler::ClimateSample,
+    seed::WorldSeed,
+    structure::StructureRule,
+    structures::jungle_tree,
 };
 
-pub struct Jungle;
+pub struct Jungle {
+    noise: Fbm<Perlin>,
+}
+
+impl Jungle {
+    pub fn new(seed: &WorldSeed) -> Jungle {
+        Jungle {
+            noise: fbm_jungle(seed),
+        }
+    }
+}
+
+static STRUCTURES: [StructureRule; 1] = [StructureRule {
+    rarity: 0.02,
+    min_height: WATER_HEIGHT as i32 + 2,
+    max_height: CHUNK_HEIGHT as i32 - 26,
+    generator: jungle_tree::generate,
+}];
 
 impl Biome for Jungle {
     fn get_surface(&self) -> SurfaceRules {
@@ -23,7 +44,9 @@ impl Biome for Jungle {
         const SCALE: f64 = 0.02;
         const AMP: f64 = 5.0;
 
-        let n = FBM_JUNGLE.get([x as f64 * SCALE + 3000.0, z as f64 * SCALE + 3000.0]);
+        let n = self
+            .noise
+            .get([x as f64 * SCALE + 3000.0, z as f64 * SCALE + 3000.0]);
 
         let uplift = (n + 0.3).clamp(-1.0, 1.0);
 
@@ -33,7 +56,28 @@ impl Biome for Jungle {
         uplift * AMP * strength
     }
 
-    fn ground_block(&self) -> BlockType {
-        BlockType::Grass
+    fn density_offset(&self, x: i32, y: i32, z: i32, climate: &ClimateSample) -> f64 {
+        const SCALE: f64 = 0.05;
+        const AMP: f64 = 6.0;
+
+        // lumpy overhangs under the canopy
+        let n = self.noise.get([
+            x as f64 * SCALE + 3500.0,
+            y as f64 * SCALE,
+            z as f64 * SCALE + 3500.0,
+        ]);
+
+        n * AMP * climate.moisture.clamp(0.0, 1.0)
+    }
+
+    fn surface_layers(&self) -> SurfaceLayers {
+        SurfaceLayers {
+            top: BlockType::Grass,
+            filler: &[(BlockType::Dirt, 4)],
+        }
+    }
+
+    fn structures(&self) -> &[StructureRule] {
+        &STRUCTURES
     }
 }
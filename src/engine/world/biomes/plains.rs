@@ -1,13 +1,34 @@
-use noise::NoiseFn;
+use noise::{Fbm, NoiseFn, Perlin};
 
 use crate::engine::world::{
-    biome::{Biome, SurfaceRules},
-    biomes::terrain_noise::FBM_PLAINS,
+    biome::{Biome, SurfaceLayers, SurfaceRules},
+    biomes::terrain_noise::fbm_plains,
     block::BlockType,
+    chunk::{CHUNK_HEIGHT, WATER_HEIGHT},
     climate_sampler::ClimateSample,
+    seed::WorldSeed,
+    structure::StructureRule,
+    structures::oak,
 };
 
-pub struct Plains;
+pub struct Plains {
+    noise: Fbm<Perlin>,
+}
+
+impl Plains {
+    pub fn new(seed: &WorldSeed) -> Plains {
+        Plains {
+            noise: fbm_plains(seed),
+        }
+    }
+}
+
+static STRUCTURES: [StructureRule; 1] = [StructureRule {
+    rarity: 0.006,
+    min_height: WATER_HEIGHT as i32 + 2,
+    max_height: CHUNK_HEIGHT as i32 - 16,
+    generator: oak::generate,
+}];
 
 impl Biome for Plains {
     fn get_surface(&self) -> SurfaceRules {
@@ -23,7 +44,9 @@ impl Biome for Plains {
         const SCALE: f64 = 0.006;
         const AMP: f64 = 3.0;
 
-        let n = FBM_PLAINS.get([x as f64 * SCALE + 1000.0, z as f64 * SCALE + 1000.0]);
+        let n = self
+            .noise
+            .get([x as f64 * SCALE + 1000.0, z as f64 * SCALE + 1000.0]);
 
         let m = climate.moisture.clamp(0.0, 1.0);
         let damp = 1.0 - 0.5 * m;
@@ -31,7 +54,14 @@ impl Biome for Plains {
         n * AMP * damp
     }
 
-    fn ground_block(&self) -> BlockType {
-        BlockType::Grass
+    fn surface_layers(&self) -> SurfaceLayers {
+        SurfaceLayers {
+            top: BlockType::Grass,
+            filler: &[(BlockType::Dirt, 3)],
+        }
+    }
+
+    fn structures(&self) -> &[StructureRule] {
+        &STRUCTURES
     }
 }
@@ -1,36 +1,35 @@
 use noise::{Fbm, MultiFractal, Perlin};
-use once_cell::sync::Lazy;
 
-const SEED: u32 = 42;
+use crate::engine::world::seed::{SALT_DESERT, SALT_JUNGLE, SALT_PLAINS, SALT_TUNDRA, WorldSeed};
 
-pub static FBM_PLAINS: Lazy<Fbm<Perlin>> = Lazy::new(|| {
-    Fbm::<Perlin>::new(SEED + 100)
+pub fn fbm_plains(seed: &WorldSeed) -> Fbm<Perlin> {
+    Fbm::<Perlin>::new(seed.derive(SALT_PLAINS))
         .set_frequency(0.5)
         .set_octaves(2)
         .set_lacunarity(2.0)
         .set_persistence(0.5)
-});
+}
 
-pub static FBM_DESERT: Lazy<Fbm<Perlin>> = Lazy::new(|| {
-    Fbm::<Perlin>::new(SEED + 200)
+pub fn fbm_desert(seed: &WorldSeed) -> Fbm<Perlin> {
+    Fbm::<Perlin>::new(seed.derive(SALT_DESERT))
         .set_frequency(0.5)
         .set_octaves(1)
         .set_lacunarity(2.0)
         .set_persistence(0.5)
-});
+}
 
-pub static FBM_JUNGLE: Lazy<Fbm<Perlin>> = Lazy::new(|| {
-    Fbm::<Perlin>::new(SEED + 300)
+pub fn fbm_jungle(seed: &WorldSeed) -> Fbm<Perlin> {
+    Fbm::<Perlin>::new(seed.derive(SALT_JUNGLE))
         .set_frequency(0.5)
         .set_octaves(3)
         .set_lacunarity(2.0)
         .set_persistence(0.55)
-});
+}
 
-pub static FBM_TUNDRA: Lazy<Fbm<Perlin>> = Lazy::new(|| {
-    Fbm::<Perlin>::new(SEED + 400)
+pub fn fbm_tundra(seed: &WorldSeed) -> Fbm<Perlin> {
+    Fbm::<Perlin>::new(seed.derive(SALT_TUNDRA))
         .set_frequency(0.5)
         .set_octaves(2)
         .set_lacunarity(2.0)
         .set_persistence(0.5)
-});
+}
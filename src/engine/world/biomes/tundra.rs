@@ -1,13 +1,34 @@
-use noise::NoiseFn;
+use noise::{Fbm, NoiseFn, Perlin};
 
 use crate::engine::world::{
-    biome::{Biome, SurfaceRules},
-    biomes::terrain_noise::FBM_TUNDRA,
+    biome::{Biome, SurfaceLayers, SurfaceRules},
+    biomes::terrain_noise::fbm_tundra,
     block::BlockType,
+    chunk::{CHUNK_HEIGHT, WATER_HEIGHT},
     climate_sampler::ClimateSample,
+    seed::WorldSeed,
+    structure::StructureRule,
+    structures::spruce,
 };
 
-pub struct Tundra;
+pub struct Tundra {
+    noise: Fbm<Perlin>,
+}
+
+impl Tundra {
+    pub fn new(seed: &WorldSeed) -> Tundra {
+        Tundra {
+            noise: fbm_tundra(seed),
+        }
+    }
+}
+
+static STRUCTURES: [StructureRule; 1] = [StructureRule {
+    rarity: 0.012,
+    min_height: WATER_HEIGHT as i32 + 2,
+    max_height: CHUNK_HEIGHT as i32 - 14,
+    generator: spruce::generate,
+}];
 
 impl Biome for Tundra {
     fn get_surface(&self) -> SurfaceRules {
@@ -23,7 +44,9 @@ impl Biome for Tundra {
         const SCALE: f64 = 0.015;
         const AMP: f64 = 5.0;
 
-        let n = FBM_TUNDRA.get([x as f64 * SCALE + 4000.0, z as f64 * SCALE + 4000.0]);
+        let n = self
+            .noise
+            .get([x as f64 * SCALE + 4000.0, z as f64 * SCALE + 4000.0]);
 
         let stepped = (n * 4.0).round() / 4.0;
 
@@ -33,7 +56,35 @@ impl Biome for Tundra {
         stepped * AMP * strength + 5.
     }
 
-    fn ground_block(&self) -> BlockType {
-        BlockType::Snow
+    fn density_offset(&self, x: i32, y: i32, z: i32, climate: &ClimateSample) -> f64 {
+        const SCALE_XZ: f64 = 0.03;
+        const SCALE_Y: f64 = 0.08;
+        const AMP: f64 = 5.0;
+
+        // stretched vertically so the steps break into sheer cliff faces
+        let n = self.noise.get([
+            x as f64 * SCALE_XZ + 4500.0,
+            y as f64 * SCALE_Y,
+            z as f64 * SCALE_XZ + 4500.0,
+        ]);
+
+        let cold = (1.0 - climate.temperature).clamp(0.0, 1.0);
+
+        n * AMP * cold
+    }
+
+    fn surface_layers(&self) -> SurfaceLayers {
+        SurfaceLayers {
+            top: BlockType::Snow,
+            filler: &[(BlockType::Dirt, 3)],
+        }
+    }
+
+    fn structures(&self) -> &[StructureRule] {
+        &STRUCTURES
+    }
+
+    fn water_freezes(&self) -> bool {
+        true
     }
 }
@@ -1,9 +1,10 @@
+use super::biome_tint::BiomeTint;
 use crate::engine::face_direction::FaceDirection;
 use bevy::math::IVec3;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum BlockType {
     Air = 0,
     Grass = 1,
@@ -15,6 +16,46 @@ pub enum BlockType {
     Water = 7,
     Stone = 8,
     Snow = 9,
+    JungleWood = 10,
+    JungleLeaf = 11,
+    SpruceWood = 12,
+    SpruceLeaf = 13,
+    Vine = 14,
+    Cactus = 15,
+    Deepslate = 16,
+    Sandstone = 17,
+    CoalOre = 18,
+    DeepslateCoalOre = 19,
+    IronOre = 20,
+    DeepslateIronOre = 21,
+    GoldOre = 22,
+    DeepslateGoldOre = 23,
+    DiamondOre = 24,
+    DeepslateDiamondOre = 25,
+    RedstoneOre = 26,
+    DeepslateRedstoneOre = 27,
+    LapisOre = 28,
+    DeepslateLapisOre = 29,
+    CopperOre = 30,
+    DeepslateCopperOre = 31,
+    Gravel = 32,
+    Ice = 33,
+    Torch = 34,
+    Glowstone = 35,
+    Lava = 36,
+}
+
+// how a block lets light and the faces behind it through
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Opacity {
+    // nothing to draw
+    Empty,
+    // hides every face touching it
+    Opaque,
+    // fully opaque or fully transparent per texel, alpha tested in the opaque pass
+    Cutout,
+    // alpha blended in its own pass, only hides faces of the same block
+    Translucent,
 }
 
 #[repr(u16)]
@@ -31,6 +72,41 @@ pub enum BlockTextureId {
     Water = 8,
     Stone = 9,
     Snow = 10,
+    JungleWoodSide = 11,
+    JungleWoodTop = 12,
+    JungleLeaf = 13,
+    SpruceWoodSide = 14,
+    SpruceWoodTop = 15,
+    SpruceLeaf = 16,
+    Vine = 17,
+    CactusSide = 18,
+    CactusTop = 19,
+    CactusBottom = 20,
+    DeepslateSide = 21,
+    DeepslateTop = 22,
+    SandstoneSide = 23,
+    SandstoneTop = 24,
+    SandstoneBottom = 25,
+    CoalOre = 26,
+    DeepslateCoalOre = 27,
+    IronOre = 28,
+    DeepslateIronOre = 29,
+    GoldOre = 30,
+    DeepslateGoldOre = 31,
+    DiamondOre = 32,
+    DeepslateDiamondOre = 33,
+    RedstoneOre = 34,
+    DeepslateRedstoneOre = 35,
+    LapisOre = 36,
+    DeepslateLapisOre = 37,
+    CopperOre = 38,
+    DeepslateCopperOre = 39,
+    Gravel = 40,
+    Ice = 41,
+    GrassSideOverlay = 42,
+    Torch = 43,
+    Glowstone = 44,
+    Lava = 45,
 }
 
 impl BlockTextureId {
@@ -38,8 +114,9 @@ impl BlockTextureId {
         BlockTextureId::iter().collect()
     }
 
+    // relative to a resource pack's assets, see ResourcePacks
     pub fn path(&self) -> String {
-        let path = String::from("minecraft_assets/textures/block");
+        let path = String::from("textures/block");
 
         let specific = String::from(match self {
             BlockTextureId::GrassTop => "grass_block_top.png",
@@ -47,23 +124,120 @@ impl BlockTextureId {
             BlockTextureId::Dirt => "dirt.png",
             BlockTextureId::Sand => "sand.png",
             BlockTextureId::Bedrock => "bedrock.png",
-            BlockTextureId::OakLeaf => "pale_oak_leaves.png",
+            BlockTextureId::OakLeaf => "oak_leaves.png",
             BlockTextureId::OakWoodSide => "oak_log.png",
             BlockTextureId::OakWoodTop => "oak_log_top.png",
-            BlockTextureId::Water => "water.png",
+            BlockTextureId::Water => "water_still.png",
             BlockTextureId::Snow => "snow.png",
             BlockTextureId::Stone => "stone.png",
+            BlockTextureId::JungleWoodSide => "jungle_log.png",
+            BlockTextureId::JungleWoodTop => "jungle_log_top.png",
+            BlockTextureId::JungleLeaf => "jungle_leaves.png",
+            BlockTextureId::SpruceWoodSide => "spruce_log.png",
+            BlockTextureId::SpruceWoodTop => "spruce_log_top.png",
+            BlockTextureId::SpruceLeaf => "spruce_leaves.png",
+            BlockTextureId::Vine => "vine.png",
+            BlockTextureId::CactusSide => "cactus_side.png",
+            BlockTextureId::CactusTop => "cactus_top.png",
+            BlockTextureId::CactusBottom => "cactus_bottom.png",
+            BlockTextureId::DeepslateSide => "deepslate.png",
+            BlockTextureId::DeepslateTop => "deepslate_top.png",
+            BlockTextureId::SandstoneSide => "sandstone.png",
+            BlockTextureId::SandstoneTop => "sandstone_top.png",
+            BlockTextureId::SandstoneBottom => "sandstone_bottom.png",
+            BlockTextureId::CoalOre => "coal_ore.png",
+            BlockTextureId::DeepslateCoalOre => "deepslate_coal_ore.png",
+            BlockTextureId::IronOre => "iron_ore.png",
+            BlockTextureId::DeepslateIronOre => "deepslate_iron_ore.png",
+            BlockTextureId::GoldOre => "gold_ore.png",
+            BlockTextureId::DeepslateGoldOre => "deepslate_gold_ore.png",
+            BlockTextureId::DiamondOre => "diamond_ore.png",
+            BlockTextureId::DeepslateDiamondOre => "deepslate_diamond_ore.png",
+            BlockTextureId::RedstoneOre => "redstone_ore.png",
+            BlockTextureId::DeepslateRedstoneOre => "deepslate_redstone_ore.png",
+            BlockTextureId::LapisOre => "lapis_ore.png",
+            BlockTextureId::DeepslateLapisOre => "deepslate_lapis_ore.png",
+            BlockTextureId::CopperOre => "copper_ore.png",
+            BlockTextureId::DeepslateCopperOre => "deepslate_copper_ore.png",
+            BlockTextureId::Gravel => "gravel.png",
+            BlockTextureId::Ice => "ice.png",
+            BlockTextureId::GrassSideOverlay => "grass_block_side_overlay.png",
+            BlockTextureId::Torch => "torch.png",
+            BlockTextureId::Glowstone => "glowstone.png",
+            BlockTextureId::Lava => "lava_still.png",
         });
 
         format!("{path}/{specific}")
     }
+
+    // grayscale textures colored by the biome they're in
+    pub fn tint(&self) -> Option<BiomeTint> {
+        match self {
+            BlockTextureId::GrassTop | BlockTextureId::GrassSideOverlay => Some(BiomeTint::Grass),
+            BlockTextureId::OakLeaf | BlockTextureId::JungleLeaf | BlockTextureId::Vine => {
+                Some(BiomeTint::Foliage)
+            }
+            BlockTextureId::SpruceLeaf => Some(BiomeTint::Evergreen),
+            _ => None,
+        }
+    }
 }
 
 impl BlockType {
-    pub fn is_seethrough(&self) -> bool {
+    pub fn opacity(&self) -> Opacity {
+        match self {
+            Self::Air => Opacity::Empty,
+            Self::OakLeaf | Self::JungleLeaf | Self::SpruceLeaf | Self::Vine | Self::Torch => {
+                Opacity::Cutout
+            }
+            Self::Water | Self::Ice => Opacity::Translucent,
+            _ => Opacity::Opaque,
+        }
+    }
+
+    // whether a face of this block touching `neighbour` can be seen
+    pub fn shows_face_to(&self, neighbour: BlockType) -> bool {
+        match neighbour.opacity() {
+            Opacity::Empty | Opacity::Cutout => true,
+            Opacity::Opaque => false,
+            // water next to water has no face in between
+            Opacity::Translucent => neighbour != *self,
+        }
+    }
+
+    pub fn is_fluid(&self) -> bool {
+        matches!(self, Self::Water)
+    }
+
+    // drawn as two crossed quads instead of a cube, see sprite_mesher
+    pub fn is_sprite(&self) -> bool {
+        matches!(self, Self::Torch)
+    }
+
+    // full cubes darken the corners next to them
+    pub fn occludes(&self) -> bool {
+        matches!(self.opacity(), Opacity::Opaque | Opacity::Cutout) && !self.is_sprite()
+    }
+
+    // levels light loses on its way into this block, it always loses at least one.
+    // 15 lets nothing through
+    pub fn light_opacity(&self) -> u8 {
+        match self {
+            Self::OakLeaf | Self::JungleLeaf | Self::SpruceLeaf | Self::Water | Self::Ice => 1,
+            Self::Vine | Self::Torch => 0,
+            _ => match self.opacity() {
+                Opacity::Opaque => 15,
+                _ => 0,
+            },
+        }
+    }
+
+    // block light level the block gives off itself
+    pub fn light_emission(&self) -> u8 {
         match self {
-            Self::Air => true,
-            _ => false,
+            Self::Glowstone | Self::Lava => 15,
+            Self::Torch => 14,
+            _ => 0,
         }
     }
 
@@ -83,13 +257,74 @@ impl BlockType {
                 _ => BlockTextureId::OakWoodSide,
             }),
 
+            BlockType::JungleWood => Some(match face {
+                FaceDirection::Top => BlockTextureId::JungleWoodTop,
+                FaceDirection::Bottom => BlockTextureId::JungleWoodTop,
+                _ => BlockTextureId::JungleWoodSide,
+            }),
+
+            BlockType::SpruceWood => Some(match face {
+                FaceDirection::Top => BlockTextureId::SpruceWoodTop,
+                FaceDirection::Bottom => BlockTextureId::SpruceWoodTop,
+                _ => BlockTextureId::SpruceWoodSide,
+            }),
+
+            BlockType::Cactus => Some(match face {
+                FaceDirection::Top => BlockTextureId::CactusTop,
+                FaceDirection::Bottom => BlockTextureId::CactusBottom,
+                _ => BlockTextureId::CactusSide,
+            }),
+
+            BlockType::Deepslate => Some(match face {
+                FaceDirection::Top => BlockTextureId::DeepslateTop,
+                FaceDirection::Bottom => BlockTextureId::DeepslateTop,
+                _ => BlockTextureId::DeepslateSide,
+            }),
+
+            BlockType::Sandstone => Some(match face {
+                FaceDirection::Top => BlockTextureId::SandstoneTop,
+                FaceDirection::Bottom => BlockTextureId::SandstoneBottom,
+                _ => BlockTextureId::SandstoneSide,
+            }),
+
             BlockType::OakLeaf => Some(BlockTextureId::OakLeaf),
+            BlockType::JungleLeaf => Some(BlockTextureId::JungleLeaf),
+            BlockType::SpruceLeaf => Some(BlockTextureId::SpruceLeaf),
+            BlockType::Vine => Some(BlockTextureId::Vine),
             BlockType::Dirt => Some(BlockTextureId::Dirt),
             BlockType::Bedrock => Some(BlockTextureId::Bedrock),
             BlockType::Sand => Some(BlockTextureId::Sand),
             BlockType::Water => Some(BlockTextureId::Water),
             BlockType::Stone => Some(BlockTextureId::Stone),
             BlockType::Snow => Some(BlockTextureId::Snow),
+            BlockType::CoalOre => Some(BlockTextureId::CoalOre),
+            BlockType::DeepslateCoalOre => Some(BlockTextureId::DeepslateCoalOre),
+            BlockType::IronOre => Some(BlockTextureId::IronOre),
+            BlockType::DeepslateIronOre => Some(BlockTextureId::DeepslateIronOre),
+            BlockType::GoldOre => Some(BlockTextureId::GoldOre),
+            BlockType::DeepslateGoldOre => Some(BlockTextureId::DeepslateGoldOre),
+            BlockType::DiamondOre => Some(BlockTextureId::DiamondOre),
+            BlockType::DeepslateDiamondOre => Some(BlockTextureId::DeepslateDiamondOre),
+            BlockType::RedstoneOre => Some(BlockTextureId::RedstoneOre),
+            BlockType::DeepslateRedstoneOre => Some(BlockTextureId::DeepslateRedstoneOre),
+            BlockType::LapisOre => Some(BlockTextureId::LapisOre),
+            BlockType::DeepslateLapisOre => Some(BlockTextureId::DeepslateLapisOre),
+            BlockType::CopperOre => Some(BlockTextureId::CopperOre),
+            BlockType::DeepslateCopperOre => Some(BlockTextureId::DeepslateCopperOre),
+            BlockType::Gravel => Some(BlockTextureId::Gravel),
+            BlockType::Glowstone => Some(BlockTextureId::Glowstone),
+            BlockType::Lava => Some(BlockTextureId::Lava),
+            BlockType::Torch => Some(BlockTextureId::Torch),
+            BlockType::Ice => Some(BlockTextureId::Ice),
+        }
+    }
+
+    // drawn over the face texture, tinted while the face below isn't
+    pub fn overlay_texture_id(&self, face: FaceDirection) -> Option<BlockTextureId> {
+        match (self, face) {
+            (BlockType::Grass, FaceDirection::Top | FaceDirection::Bottom) => None,
+            (BlockType::Grass, _) => Some(BlockTextureId::GrassSideOverlay),
+            _ => None,
         }
     }
 }
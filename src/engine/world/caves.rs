@@ -0,0 +1,250 @@
+use bevy::math::IVec3;
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+
+use super::block::BlockType;
+use super::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunk, WATER_HEIGHT};
+
+// lowest y that may be carved, everything below stays solid so bedrock is never exposed from above
+const MIN_CARVE_Y: i32 = 2;
+
+// cheese caverns stay this far below the surface
+const CHEESE_MIN_DEPTH: i32 = 8;
+// tunnels stay this far below the surface unless they hit an entrance region
+const TUNNEL_MIN_DEPTH: i32 = 4;
+
+// every carve decision only depends on the world position, so a block is carved
+// the same way regardless of which chunk is being generated
+pub struct CaveCarver {
+    cheese: Fbm<Perlin>,
+    spaghetti_a: Perlin,
+    spaghetti_b: Perlin,
+    spaghetti_width: Perlin,
+    noodle_a: Perlin,
+    noodle_b: Perlin,
+    entrances: Perlin,
+}
+
+impl CaveCarver {
+    pub fn new(seed: u32) -> CaveCarver {
+        // fbm seeds its octaves with seed + 1, seed + 2, ... so each noise gets a range of its own
+        let noise_seed = |k: u32| seed.wrapping_add(k * 1000);
+
+        CaveCarver {
+            cheese: Fbm::<Perlin>::new(noise_seed(0))
+                .set_frequency(0.5)
+                .set_octaves(2)
+                .set_lacunarity(2.0)
+                .set_persistence(0.5),
+
+            spaghetti_a: Perlin::new(noise_seed(1)),
+            spaghetti_b: Perlin::new(noise_seed(2)),
+            spaghetti_width: Perlin::new(noise_seed(3)),
+
+            noodle_a: Perlin::new(noise_seed(4)),
+            noodle_b: Perlin::new(noise_seed(5)),
+
+            entrances: Perlin::new(noise_seed(6)),
+        }
+    }
+}
+
+impl CaveCarver {
+    // `top_solid` is the highest solid block of the column `pos` is in
+    pub fn is_cave(&self, pos: IVec3, top_solid: i32) -> bool {
+        if pos.y < MIN_CARVE_Y {
+            return false;
+        }
+
+        let depth = top_solid - pos.y;
+        let underwater = top_solid < WATER_HEIGHT as i32;
+
+        let tunnel_min_depth = if !underwater && self.is_entrance(pos.x, pos.z) {
+            0
+        } else {
+            TUNNEL_MIN_DEPTH
+        };
+
+        (depth >= CHEESE_MIN_DEPTH && self.is_cheese(pos))
+            || (depth >= tunnel_min_depth && (self.is_spaghetti(pos) || self.is_noodle(pos)))
+    }
+
+    pub fn carve(&self, chunk: &mut Chunk) {
+        let origin = chunk.chunk_origin();
+
+        for lx in 0..CHUNK_SIZE as i32 {
+            for lz in 0..CHUNK_SIZE as i32 {
+                let Some(top_solid) = (0..CHUNK_HEIGHT as i32).rev().find(|&y| {
+                    Self::is_carvable(chunk.blocks[Chunk::to_index(IVec3::new(lx, y, lz))])
+                }) else {
+                    continue;
+                };
+
+                for y in MIN_CARVE_Y..=top_solid {
+                    let local = IVec3::new(lx, y, lz);
+                    let index = Chunk::to_index(local);
+
+                    if !Self::is_carvable(chunk.blocks[index]) {
+                        continue;
+                    }
+
+                    if self.is_cave(origin + local, top_solid) {
+                        chunk.blocks[index] = BlockType::Air;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Helper Functions
+impl CaveCarver {
+    fn is_carvable(block: BlockType) -> bool {
+        !matches!(
+            block,
+            BlockType::Air | BlockType::Water | BlockType::Ice | BlockType::Bedrock
+        )
+    }
+
+    // big open caverns where the noise blobs peak
+    fn is_cheese(&self, pos: IVec3) -> bool {
+        const SCALE_XZ: f64 = 0.012;
+        const SCALE_Y: f64 = 0.022;
+        const THRESHOLD: f64 = 0.42;
+
+        let n = self.cheese.get([
+            pos.x as f64 * SCALE_XZ,
+            pos.y as f64 * SCALE_Y,
+            pos.z as f64 * SCALE_XZ,
+        ]);
+
+        n > THRESHOLD
+    }
+
+    // long winding tunnels along the intersection of two noise zero-surfaces
+    fn is_spaghetti(&self, pos: IVec3) -> bool {
+        const SCALE_XZ: f64 = 0.018;
+        const SCALE_Y: f64 = 0.03;
+
+        let p = [
+            pos.x as f64 * SCALE_XZ,
+            pos.y as f64 * SCALE_Y,
+            pos.z as f64 * SCALE_XZ,
+        ];
+
+        let width = self
+            .spaghetti_width
+            .get([p[0] * 0.5, p[1] * 0.5, p[2] * 0.5]);
+        let width = 0.04 + 0.03 * (width + 1.0) * 0.5;
+
+        self.spaghetti_a.get(p).abs() < width && self.spaghetti_b.get(p).abs() < width
+    }
+
+    // same idea as spaghetti, just tighter and more frequent
+    fn is_noodle(&self, pos: IVec3) -> bool {
+        const SCALE: f64 = 0.045;
+        const WIDTH: f64 = 0.035;
+
+        let p = [
+            pos.x as f64 * SCALE,
+            pos.y as f64 * SCALE,
+            pos.z as f64 * SCALE,
+        ];
+
+        self.noodle_a.get(p).abs() < WIDTH && self.noodle_b.get(p).abs() < WIDTH
+    }
+
+    fn is_entrance(&self, x: i32, z: i32) -> bool {
+        const SCALE: f64 = 0.01;
+        const THRESHOLD: f64 = 0.55;
+
+        self.entrances.get([x as f64 * SCALE, z as f64 * SCALE]) > THRESHOLD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::IVec2;
+
+    use super::*;
+
+    const SEED: u32 = 7;
+
+    // highest stone block of a column, only depends on the world position
+    fn top_solid(x: i32, z: i32) -> i32 {
+        70 + (x * 7 + z * 3).rem_euclid(9)
+    }
+
+    fn stone_chunk(coord: IVec2) -> Chunk {
+        let mut chunk = Chunk::empty(coord);
+        let origin = chunk.chunk_origin();
+
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                for y in 0..=top_solid(origin.x + x, origin.z + z) {
+                    chunk.blocks[Chunk::to_index(IVec3::new(x, y, z))] = BlockType::Stone;
+                }
+            }
+        }
+
+        chunk
+    }
+
+    #[test]
+    fn carving_does_not_depend_on_the_chunk() {
+        let carver = CaveCarver::new(SEED);
+        let mut carved = 0;
+
+        for coord in [IVec2::new(0, 0), IVec2::new(1, 0)] {
+            let mut chunk = stone_chunk(coord);
+            carver.carve(&mut chunk);
+
+            let origin = chunk.chunk_origin();
+
+            // the columns on both sides of the border between the two chunks
+            let xs = match coord.x {
+                0 => CHUNK_SIZE as i32 - 4..CHUNK_SIZE as i32,
+                _ => 0..4,
+            };
+
+            for x in xs {
+                for z in 0..CHUNK_SIZE as i32 {
+                    let world = origin + IVec3::new(x, 0, z);
+                    let top = top_solid(world.x, world.z);
+
+                    for y in 0..=top {
+                        let block = chunk.blocks[Chunk::to_index(IVec3::new(x, y, z))];
+                        let expected = carver.is_cave(world.with_y(y), top);
+
+                        assert_eq!(block == BlockType::Air, expected, "{:?}", world.with_y(y));
+                        carved += expected as usize;
+                    }
+                }
+            }
+        }
+
+        assert!(carved > 0, "no caves near the border to compare");
+    }
+
+    #[test]
+    fn bottom_layer_is_never_carved() {
+        let carver = CaveCarver::new(SEED);
+
+        for coord in [IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(-3, 5)] {
+            let mut chunk = stone_chunk(coord);
+            carver.carve(&mut chunk);
+
+            for x in 0..CHUNK_SIZE as i32 {
+                for z in 0..CHUNK_SIZE as i32 {
+                    let index = Chunk::to_index(IVec3::new(x, 0, z));
+                    assert_eq!(chunk.blocks[index], BlockType::Stone);
+                }
+            }
+        }
+
+        for x in -64..64 {
+            for z in -64..64 {
+                assert!(!carver.is_cave(IVec3::new(x, 0, z), top_solid(x, z)));
+            }
+        }
+    }
+}
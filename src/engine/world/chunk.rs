@@ -1,14 +1,24 @@
 use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
 use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
-use once_cell::sync::Lazy;
 
-use super::block::{BlockRead, BlockType};
+use super::biome_tint::{BiomeTint, ChunkTints};
+use super::block::{BlockRead, BlockTextureId, BlockType, Opacity};
+use super::chunk_meshing::{ChunkMesher, LightingMode};
+use super::light::{self, LIGHT_KINDS, LightRead};
 use crate::engine::atlas::{ChunkMaterial, TextureAtlas};
-use crate::engine::world::biome::BiomeSelector;
+use crate::engine::face_direction::{DIRECTIONS, FACE_VERTICES, FaceDirection};
+use crate::engine::mesh_builder::{MeshBuilder, NO_TINT};
+use crate::engine::world::biome::{BiomeSelector, RIVER_BANK_LAYERS, RIVER_BANK_THRESHOLD};
 use crate::engine::world::block::BlockWrite;
-use crate::engine::world::climate_sampler::ClimateSampler;
-use crate::engine::{face_direction::DIRECTIONS, mesh_builder::MeshBuilder};
+use crate::engine::world::climate_sampler::{ClimateSample, ClimateSampler};
+use crate::engine::world::generator::WorldGenerator;
+use crate::engine::world::ores;
+use crate::engine::world::pending_writes::{PendingBlockWrites, split_world_pos};
+use crate::engine::world::seed::{
+    SALT_CLIFF, SALT_DENSITY, SALT_HEIGHT, SALT_LAYERS, SALT_ORES, SALT_STRUCTURES, WorldSeed,
+};
+use crate::engine::world::{fluid_mesher, sprite_mesher};
 
 pub const CHUNK_HEIGHT: usize = 128;
 const HALF_CHUNK_HEIGHT: usize = CHUNK_HEIGHT / 2;
@@ -16,47 +26,129 @@ pub const WATER_HEIGHT: usize = HALF_CHUNK_HEIGHT - 20;
 pub const CHUNK_SIZE: usize = 16;
 const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_HEIGHT;
 
-const PAD_CHUNK_SIZE: usize = CHUNK_SIZE + 2;
+pub(super) const PAD_CHUNK_SIZE: usize = CHUNK_SIZE + 2;
 
-const PAD_CHUNK_HEIGHT: usize = CHUNK_HEIGHT + 2;
+pub(super) const PAD_CHUNK_HEIGHT: usize = CHUNK_HEIGHT + 2;
 
-const PAD_CHUNK_VOLUME: usize = PAD_CHUNK_SIZE * PAD_CHUNK_SIZE * PAD_CHUNK_HEIGHT;
+pub(super) const PAD_CHUNK_VOLUME: usize = PAD_CHUNK_SIZE * PAD_CHUNK_SIZE * PAD_CHUNK_HEIGHT;
 
-const SEED: u32 = 42;
+// padded sky and block light, indexed by LightKind
+pub(super) type PaddedLight = [[u8; PAD_CHUNK_VOLUME]; 2];
 
-pub static FBM: Lazy<Fbm<Perlin>> = Lazy::new(|| {
-    Fbm::<Perlin>::new(SEED)
-        .set_frequency(0.5)
-        .set_octaves(2)
-        .set_lacunarity(2.0)
-        .set_persistence(0.5)
-});
+const OPAQUE_LAYER: usize = 0;
+const TRANSLUCENT_LAYER: usize = 1;
+
+// opaque and cutout blocks share a mesh, translucent ones get drawn after them, blended
+pub struct ChunkMeshes {
+    pub opaque: Mesh,
+    pub translucent: Option<Mesh>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct VisibleFace {
+    texture_id: BlockTextureId,
+    overlay: Option<BlockTextureId>,
+    ao: [u8; 4],
+    // sky and block light per corner in quarter levels, corners in the same order as `ao`
+    light: [[u8; 4]; 2],
+    layer: usize,
+}
+
+// noise behind the base terrain shape, the biomes bring their own on top
+pub struct TerrainNoise {
+    height: Fbm<Perlin>,
+    // 3d noise that bends the heightmap into overhangs and cliffs
+    density: Fbm<Perlin>,
+    // decides where the 3d noise is allowed to do its thing, low values keep terrain heightmap-like
+    cliff: Fbm<Perlin>,
+}
+
+impl TerrainNoise {
+    pub fn new(seed: &WorldSeed) -> TerrainNoise {
+        TerrainNoise {
+            height: Fbm::<Perlin>::new(seed.derive(SALT_HEIGHT))
+                .set_frequency(0.5)
+                .set_octaves(2)
+                .set_lacunarity(2.0)
+                .set_persistence(0.5),
+
+            density: Fbm::<Perlin>::new(seed.derive(SALT_DENSITY))
+                .set_frequency(0.5)
+                .set_octaves(3)
+                .set_lacunarity(2.0)
+                .set_persistence(0.5),
+
+            cliff: Fbm::<Perlin>::new(seed.derive(SALT_CLIFF))
+                .set_frequency(0.5)
+                .set_octaves(2)
+                .set_lacunarity(2.0)
+                .set_persistence(0.5),
+        }
+    }
+}
+
+// density is only sampled this many blocks around the blended height,
+// everything further below is solid and everything further above is air
+const DENSITY_BAND: i32 = 24;
+const DENSITY_AMPLITUDE: f64 = 14.0;
 
 #[derive(Component, Clone)]
 pub struct Chunk {
     pub coord: IVec2,
     pub blocks: [BlockType; CHUNK_VOLUME],
+    // see Chunk::light
+    pub sky_light: [u8; CHUNK_VOLUME],
+    pub block_light: [u8; CHUNK_VOLUME],
     pub surface: [i32; CHUNK_SIZE * CHUNK_SIZE],
 }
 
 #[derive(Resource, Default)]
 pub struct ChunkMap(pub HashMap<IVec2, Entity>);
 
+// write target used while a chunk is being generated: blocks inside the chunk
+// land directly, everything else spills into the pending buffer
+struct GenerationWriter<'a> {
+    chunk: &'a mut Chunk,
+    pending: &'a mut PendingBlockWrites,
+}
+
+impl BlockWrite for GenerationWriter<'_> {
+    fn set_block(&mut self, world_pos: IVec3, block_type: BlockType) {
+        let (coord, local) = split_world_pos(world_pos);
+
+        if coord != self.chunk.coord {
+            self.pending.push(world_pos, block_type);
+            return;
+        }
+
+        self.chunk.set_local(local, block_type);
+    }
+}
+
 // Methods
 impl Chunk {
     pub fn apply_structures(
         &mut self,
-        world_origin: IVec2,
         biome_selector: &BiomeSelector,
         sampler: &ClimateSampler,
-        world: &mut dyn BlockWrite,
+        seed: &WorldSeed,
+        pending: &mut PendingBlockWrites,
     ) {
+        let world_origin = self.chunk_origin();
+        let structure_seed = seed.derive(SALT_STRUCTURES);
+        let surface = self.surface;
+
+        let mut writer = GenerationWriter {
+            chunk: self,
+            pending,
+        };
+
         for lx in 0..CHUNK_SIZE as i32 {
             for lz in 0..CHUNK_SIZE as i32 {
-                let surface_y = self.surface[lx as usize + lz as usize * CHUNK_SIZE];
+                let surface_y = surface[lx as usize + lz as usize * CHUNK_SIZE];
 
                 let world_x = world_origin.x + lx;
-                let world_z = world_origin.y + lz;
+                let world_z = world_origin.z + lz;
 
                 let climate = sampler.sample(world_x, world_z);
                 let biome = biome_selector.pick(&climate);
@@ -64,131 +156,505 @@ impl Chunk {
                 let pos = IVec3::new(world_x, surface_y, world_z);
 
                 for structure in biome.structures() {
-                    structure.try_place(pos, world, SEED);
+                    structure.try_place(pos, &mut writer, structure_seed);
                 }
             }
         }
     }
 
-    pub fn build_chunk_mesh(&self, block_access: &impl BlockRead, atlas: &TextureAtlas) -> Mesh {
-        let mut mesh_builder = MeshBuilder::with_capacity_faces(CHUNK_VOLUME * 2);
-        let origin = self.chunk_origin();
+    // first free block above the highest solid one, or -1 for columns under water
+    pub fn update_surface(&mut self) {
+        for lx in 0..CHUNK_SIZE as i32 {
+            for lz in 0..CHUNK_SIZE as i32 {
+                let top_solid = (0..CHUNK_HEIGHT as i32)
+                    .rev()
+                    .find(|&y| {
+                        !matches!(
+                            self.blocks[Self::to_index(IVec3::new(lx, y, lz))],
+                            BlockType::Air | BlockType::Water
+                        )
+                    })
+                    .unwrap_or(-1);
+
+                self.surface[lx as usize + lz as usize * CHUNK_SIZE] =
+                    if top_solid >= WATER_HEIGHT as i32 {
+                        top_solid + 1
+                    } else {
+                        -1
+                    };
+            }
+        }
+    }
 
-        let mut is_solid = [false; CHUNK_VOLUME];
-        for i in 0..CHUNK_VOLUME {
-            is_solid[i] = !self.blocks[i].is_seethrough();
+    pub fn apply_writes(&mut self, writes: impl IntoIterator<Item = (IVec3, BlockType)>) {
+        for (local, block_type) in writes {
+            self.set_local(local, block_type);
         }
+    }
 
-        let mut is_solid_padded = [false; PAD_CHUNK_VOLUME];
+    pub fn build_chunk_mesh(
+        &self,
+        world: &(impl BlockRead + LightRead),
+        atlas: &TextureAtlas,
+        tints: &ChunkTints,
+        mesher: ChunkMesher,
+        lighting: LightingMode,
+    ) -> ChunkMeshes {
+        let padded = self.padded(&self.blocks, BlockType::Air, |pos| world.get_block(pos));
+        let light: PaddedLight = LIGHT_KINDS
+            .map(|kind| self.padded(self.light(kind), 0, |pos| world.get_light(pos, kind)));
+
+        let mut builders = [
+            MeshBuilder::with_capacity_faces(match mesher {
+                ChunkMesher::Naive => CHUNK_VOLUME * 2,
+                ChunkMesher::Greedy => CHUNK_SIZE * CHUNK_HEIGHT * 4,
+            }),
+            MeshBuilder::with_capacity_faces(CHUNK_SIZE * CHUNK_SIZE * 2),
+        ];
+
+        let mut emit = |face: VisibleFace, dir: FaceDirection, min: IVec3, max: IVec3| {
+            Self::add_visible_quad(&mut builders, atlas, tints, face, dir, min, max);
+        };
+
+        match mesher {
+            ChunkMesher::Naive => Self::build_naive_mesh(&padded, &light, lighting, &mut emit),
+            ChunkMesher::Greedy => {
+                Self::build_greedy_mesh(&padded, &light, tints, lighting, &mut emit)
+            }
+        }
+
+        sprite_mesher::build_sprite_mesh(&padded, &light, atlas, &mut builders[OPAQUE_LAYER]);
+        fluid_mesher::build_fluid_mesh(&padded, &light, atlas, &mut builders[TRANSLUCENT_LAYER]);
+
+        let [opaque, translucent] = builders;
+
+        ChunkMeshes {
+            opaque: opaque.build_mesh(),
+            translucent: (!translucent.is_empty()).then(|| translucent.build_mesh()),
+        }
+    }
+
+    // per block `data` of the chunk plus a one block border taken from the neighbours,
+    // `missing` wherever `neighbour` has nothing
+    fn padded<T: Copy>(
+        &self,
+        data: &[T],
+        missing: T,
+        neighbour: impl Fn(IVec3) -> Option<T>,
+    ) -> [T; PAD_CHUNK_VOLUME] {
+        let origin = self.chunk_origin();
+
+        let mut padded = [missing; PAD_CHUNK_VOLUME];
 
         for y in 0..CHUNK_HEIGHT as i32 {
             for z in 0..CHUNK_SIZE as i32 {
                 for x in 0..CHUNK_SIZE as i32 {
                     let src = Self::to_index(IVec3::new(x, y, z));
                     let dst = Self::pad_index(x + 1, y + 1, z + 1);
-                    is_solid_padded[dst] = is_solid[src];
+                    padded[dst] = data[src];
                 }
             }
         }
 
-        // padding for x dimension
-        for by in 0..CHUNK_HEIGHT as i32 {
-            for z in 0..CHUNK_SIZE as i32 {
-                is_solid_padded[Self::pad_index(0, by + 1, z + 1)] = block_access
-                    .get_block(origin + IVec3::new(-1, by, z))
-                    .map_or(false, |b| !b.is_seethrough());
-
-                is_solid_padded[Self::pad_index(CHUNK_SIZE as i32 + 1, by + 1, z + 1)] =
-                    block_access
-                        .get_block(origin + IVec3::new(CHUNK_SIZE as i32, by, z))
-                        .map_or(false, |b| !b.is_seethrough());
+        let mut pad = |px: i32, py: i32, pz: i32| {
+            padded[Self::pad_index(px, py, pz)] =
+                neighbour(origin + IVec3::new(px - 1, py - 1, pz - 1)).unwrap_or(missing);
+        };
+
+        let last = PAD_CHUNK_SIZE as i32 - 1;
+
+        for py in 1..=CHUNK_HEIGHT as i32 {
+            for i in 1..=CHUNK_SIZE as i32 {
+                // x and z dimension
+                pad(0, py, i);
+                pad(last, py, i);
+                pad(i, py, 0);
+                pad(i, py, last);
             }
-        }
 
-        // padding for z dimension
-        for by in 0..CHUNK_HEIGHT as i32 {
-            for bx in 0..CHUNK_SIZE as i32 {
-                is_solid_padded[Self::pad_index(bx + 1, by + 1, 0)] = block_access
-                    .get_block(origin + IVec3::new(bx, by, -1))
-                    .map_or(false, |b| !b.is_seethrough());
-
-                is_solid_padded[Self::pad_index(bx + 1, by + 1, CHUNK_SIZE as i32 + 1)] =
-                    block_access
-                        .get_block(origin + IVec3::new(bx, by, CHUNK_SIZE as i32))
-                        .map_or(false, |b| !b.is_seethrough());
+            // corner columns, only ambient occlusion looks at those
+            for (px, pz) in [(0, 0), (last, 0), (0, last), (last, last)] {
+                pad(px, py, pz);
             }
         }
 
-        // padding for y dimension
-        for bz in 0..CHUNK_SIZE as i32 {
-            for bx in 0..CHUNK_SIZE as i32 {
-                is_solid_padded[Self::pad_index(bx + 1, 0, bz + 1)] = block_access
-                    .get_block(origin + IVec3::new(bx, -1, bz))
-                    .map_or(false, |b| !b.is_seethrough());
-
-                is_solid_padded[Self::pad_index(bx + 1, CHUNK_HEIGHT as i32 + 1, bz + 1)] =
-                    block_access
-                        .get_block(origin + IVec3::new(bx, CHUNK_HEIGHT as i32, bz))
-                        .map_or(false, |b| !b.is_seethrough());
+        // y dimension, edges included so smooth lighting sees the sky above border blocks
+        for pz in 0..PAD_CHUNK_SIZE as i32 {
+            for px in 0..PAD_CHUNK_SIZE as i32 {
+                pad(px, 0, pz);
+                pad(px, PAD_CHUNK_HEIGHT as i32 - 1, pz);
             }
         }
 
-        for py in 1..=CHUNK_HEIGHT as i32 {
-            for pz in 1..=CHUNK_SIZE as i32 {
-                for px in 1..=CHUNK_SIZE as i32 {
-                    let idx = Self::pad_index(px, py, pz);
+        padded
+    }
+
+    // one quad per visible face, each handed to `emit` with the blocks it covers
+    fn build_naive_mesh(
+        padded: &[BlockType; PAD_CHUNK_VOLUME],
+        light: &PaddedLight,
+        lighting: LightingMode,
+        emit: &mut impl FnMut(VisibleFace, FaceDirection, IVec3, IVec3),
+    ) {
+        for by in 0..CHUNK_HEIGHT as i32 {
+            for bz in 0..CHUNK_SIZE as i32 {
+                for bx in 0..CHUNK_SIZE as i32 {
+                    let pos = IVec3::new(bx, by, bz);
+
+                    for dir in DIRECTIONS {
+                        if let Some(face) = Self::visible_face(padded, light, lighting, pos, dir) {
+                            emit(face, dir, pos, pos);
+                        }
+                    }
+                }
+            }
+        }
+    }
 
-                    if !is_solid_padded[idx] {
-                        continue;
+    // merges visible faces that share a plane and a texture into as few quads as possible.
+    // each slice through the chunk gets a mask of its visible faces, rectangles are grown
+    // first along u, then along v while the whole row still matches.
+    // faces with uneven ambient occlusion, light or biome tint stay single quads, stretching
+    // their corner values over a bigger quad would smear the shadow or color across it
+    fn build_greedy_mesh(
+        padded: &[BlockType; PAD_CHUNK_VOLUME],
+        light: &PaddedLight,
+        tints: &ChunkTints,
+        lighting: LightingMode,
+        emit: &mut impl FnMut(VisibleFace, FaceDirection, IVec3, IVec3),
+    ) {
+        let dims = IVec3::new(CHUNK_SIZE as i32, CHUNK_HEIGHT as i32, CHUNK_SIZE as i32);
+
+        for dir in DIRECTIONS {
+            let n = dir.axis();
+            let u = (n + 1) % 3;
+            let v = (n + 2) % 3;
+
+            let (du, dv) = (dims[u], dims[v]);
+            let mut mask: Vec<Option<VisibleFace>> = vec![None; (du * dv) as usize];
+            let at = |i: i32, j: i32| (i + j * du) as usize;
+
+            for slice in 0..dims[n] {
+                let block_pos = |i: i32, j: i32| {
+                    let mut pos = IVec3::ZERO;
+                    pos[n] = slice;
+                    pos[u] = i;
+                    pos[v] = j;
+                    pos
+                };
+
+                for j in 0..dv {
+                    for i in 0..du {
+                        let pos = block_pos(i, j);
+                        mask[at(i, j)] = Self::visible_face(padded, light, lighting, pos, dir);
                     }
+                }
 
-                    let bx = px - 1;
-                    let by = py - 1;
-                    let bz = pz - 1;
+                // neighbouring flat faces share two corners, so they also share the tint
+                let flat_tint =
+                    |face: VisibleFace, i, j| Self::tint_is_flat(tints, face, dir, block_pos(i, j));
 
-                    let block_type = self.blocks[by as usize
-                        + bz as usize * CHUNK_HEIGHT
-                        + bx as usize * CHUNK_HEIGHT * CHUNK_SIZE];
+                for j in 0..dv {
+                    let mut i = 0;
 
-                    for dir in DIRECTIONS {
-                        let dir_vec3 = dir.normal();
-                        let neighbour_solid = is_solid_padded
-                            [Self::pad_index(px + dir_vec3.x, py + dir_vec3.y, pz + dir_vec3.z)];
-
-                        if !neighbour_solid {
-                            if let Some(texture_id) = block_type.texture_id(dir) {
-                                let uvs = atlas.uvs(texture_id);
-                                mesh_builder.add_face(dir, IVec3::new(bx, by, bz), uvs);
+                    while i < du {
+                        let Some(face) = mask[at(i, j)] else {
+                            i += 1;
+                            continue;
+                        };
+
+                        let mergeable = face.ao.iter().all(|corner| *corner == face.ao[0])
+                            && face
+                                .light
+                                .iter()
+                                .all(|light| light.iter().all(|corner| *corner == light[0]))
+                            && flat_tint(face, i, j);
+
+                        let matches = |k, l| mask[at(k, l)] == Some(face) && flat_tint(face, k, l);
+
+                        let mut w = 1;
+                        while mergeable && i + w < du && matches(i + w, j) {
+                            w += 1;
+                        }
+
+                        let mut h = 1;
+                        while mergeable && j + h < dv && (i..i + w).all(|k| matches(k, j + h)) {
+                            h += 1;
+                        }
+
+                        for jj in j..j + h {
+                            for k in i..i + w {
+                                mask[at(k, jj)] = None;
                             }
                         }
+
+                        let min = block_pos(i, j);
+                        let mut max = min;
+                        max[u] = i + w - 1;
+                        max[v] = j + h - 1;
+
+                        emit(face, dir, min, max);
+
+                        i += w;
                     }
                 }
             }
         }
+    }
+
+    fn visible_face(
+        padded: &[BlockType; PAD_CHUNK_VOLUME],
+        light: &PaddedLight,
+        lighting: LightingMode,
+        pos: IVec3,
+        dir: FaceDirection,
+    ) -> Option<VisibleFace> {
+        let at = |p: IVec3| padded[Self::pad_index(p.x, p.y, p.z)];
+
+        let padded_pos = pos + IVec3::ONE;
+        let block = at(padded_pos);
+
+        // fluids and sprites have their own meshers
+        if block.is_fluid() || block.is_sprite() {
+            return None;
+        }
+
+        let front = padded_pos + dir.normal();
+        if !block.shows_face_to(at(front)) {
+            return None;
+        }
+
+        let layer = match block.opacity() {
+            Opacity::Empty => return None,
+            Opacity::Opaque | Opacity::Cutout => OPAQUE_LAYER,
+            Opacity::Translucent => TRANSLUCENT_LAYER,
+        };
+
+        Some(VisibleFace {
+            texture_id: block.texture_id(dir)?,
+            overlay: block.overlay_texture_id(dir),
+            ao: Self::face_ao(padded, padded_pos, dir),
+            light: LIGHT_KINDS.map(|kind| {
+                Self::face_light(padded, &light[kind as usize], lighting, padded_pos, dir)
+            }),
+            layer,
+        })
+    }
+
+    // the quad covering `face` on every block from `min` to `max`, and its overlay if it has one
+    fn add_visible_quad(
+        builders: &mut [MeshBuilder; 2],
+        atlas: &TextureAtlas,
+        tints: &ChunkTints,
+        face: VisibleFace,
+        dir: FaceDirection,
+        min: IVec3,
+        max: IVec3,
+    ) {
+        let builder = &mut builders[face.layer];
+
+        // back from quarter levels
+        let [sky, block] = face.light;
+        let lights: [(f32, f32); 4] = std::array::from_fn(|corner| {
+            light::vertex_light(sky[corner] as f32 / 4.0, block[corner] as f32 / 4.0)
+        });
+
+        let shade = |tint: [[f32; 3]; 4]| -> [[f32; 4]; 4] {
+            std::array::from_fn(|corner| {
+                let (brightness, block_share) = lights[corner];
+                let [r, g, b] = tint[corner].map(|c| c * brightness);
+                [r, g, b, block_share]
+            })
+        };
+
+        let tile = atlas.tile_origin(face.texture_id);
+        let tint = Self::quad_tint(tints, face.texture_id.tint(), dir, min, max);
+        builder.add_quad(dir, min, max, tile, face.ao, shade(tint));
+
+        if let Some(overlay) = face.overlay {
+            let tile = atlas.tile_origin(overlay);
+            let tint = Self::quad_tint(tints, overlay.tint(), dir, min, max);
+            builder.add_overlay_quad(dir, min, max, tile, face.ao, shade(tint));
+        }
+    }
+
+    // whether every tinted texture of `face` on the block at `pos` has the same color at all
+    // four corners, compared in 8 bit so a barely moving climate still merges
+    fn tint_is_flat(tints: &ChunkTints, face: VisibleFace, dir: FaceDirection, pos: IVec3) -> bool {
+        [Some(face.texture_id), face.overlay]
+            .into_iter()
+            .flatten()
+            .all(|texture| {
+                let corners = Self::quad_tint(tints, texture.tint(), dir, pos, pos)
+                    .map(|corner| corner.map(|c| (c * 255.0).round() as u8));
+
+                corners.iter().all(|corner| *corner == corners[0])
+            })
+    }
+
+    // biome color at each corner of a quad, in FACE_VERTICES order
+    fn quad_tint(
+        tints: &ChunkTints,
+        tint: Option<BiomeTint>,
+        dir: FaceDirection,
+        min: IVec3,
+        max: IVec3,
+    ) -> [[f32; 3]; 4] {
+        let Some(tint) = tint else {
+            return NO_TINT;
+        };
+
+        FACE_VERTICES[dir as usize].map(|corner| {
+            let x = if corner[0] < 0.0 { min.x } else { max.x + 1 };
+            let z = if corner[2] < 0.0 { min.z } else { max.z + 1 };
 
-        mesh_builder.build_mesh()
+            tints.corner(tint, IVec2::new(x, z))
+        })
+    }
+
+    // ambient occlusion per face corner, in FACE_VERTICES order. 0 is fully occluded, 3 is open.
+    // looks at the two blocks beside the corner and the one diagonal to it, in the layer the
+    // face looks into
+    fn face_ao(
+        padded: &[BlockType; PAD_CHUNK_VOLUME],
+        padded_pos: IVec3,
+        dir: FaceDirection,
+    ) -> [u8; 4] {
+        let front = padded_pos + dir.normal();
+        let solid = |p: IVec3| padded[Self::pad_index(p.x, p.y, p.z)].occludes();
+
+        Self::corner_sides(dir).map(|(side_u, side_v)| {
+            let a = solid(front + side_u);
+            let b = solid(front + side_v);
+
+            // both sides block the corner completely, whatever sits diagonally
+            if a && b {
+                return 0;
+            }
+
+            3 - a as u8 - b as u8 - solid(front + side_u + side_v) as u8
+        })
+    }
+
+    // light of each face corner in quarter levels, in FACE_VERTICES order. smooth lighting
+    // averages the four blocks in front of the face touching the corner, like ambient
+    // occlusion solid ones are left out and two solid sides hide the diagonal one
+    fn face_light(
+        padded: &[BlockType; PAD_CHUNK_VOLUME],
+        light: &[u8; PAD_CHUNK_VOLUME],
+        lighting: LightingMode,
+        padded_pos: IVec3,
+        dir: FaceDirection,
+    ) -> [u8; 4] {
+        let front = padded_pos + dir.normal();
+        let level = |p: IVec3| {
+            let index = Self::pad_index(p.x, p.y, p.z);
+            (padded[index].opacity() != Opacity::Opaque).then_some(light[index] as u32)
+        };
+
+        // the block in front is never solid, the face would be hidden otherwise
+        let own = level(front).unwrap_or(0);
+        if lighting == LightingMode::Flat {
+            return [own as u8 * 4; 4];
+        }
+
+        Self::corner_sides(dir).map(|(side_u, side_v)| {
+            let a = level(front + side_u);
+            let b = level(front + side_v);
+
+            let diagonal = if a.is_none() && b.is_none() {
+                None
+            } else {
+                level(front + side_u + side_v)
+            };
+
+            let (sum, count) = [a, b, diagonal]
+                .into_iter()
+                .flatten()
+                .fold((own, 1), |(sum, count), level| (sum + level, count + 1));
+
+            ((sum * 4 + count / 2) / count) as u8
+        })
+    }
+
+    // unit steps along the face's two axes towards each of its corners, in FACE_VERTICES order
+    fn corner_sides(dir: FaceDirection) -> [(IVec3, IVec3); 4] {
+        let n = dir.axis();
+        let u = (n + 1) % 3;
+        let v = (n + 2) % 3;
+
+        FACE_VERTICES[dir as usize].map(|corner| {
+            let mut side_u = IVec3::ZERO;
+            side_u[u] = if corner[u] < 0.0 { -1 } else { 1 };
+
+            let mut side_v = IVec3::ZERO;
+            side_v[v] = if corner[v] < 0.0 { -1 } else { 1 };
+
+            (side_u, side_v)
+        })
     }
 }
 
 // Associated Functions
 impl Chunk {
-    pub fn new(chunk_x: i32, chunk_z: i32) -> Chunk {
-        let selector = BiomeSelector::default();
-        let sampler = ClimateSampler::new();
-        let scale = 0.01;
+    pub fn new(
+        chunk_x: i32,
+        chunk_z: i32,
+        generator: &WorldGenerator,
+        spill: &mut PendingBlockWrites,
+    ) -> Chunk {
+        let WorldGenerator {
+            seed,
+            selector,
+            sampler,
+            noise,
+            carver,
+        } = generator;
+
+        let mut chunk = Self::generate_terrain(chunk_x, chunk_z, selector, sampler, noise, seed);
+        carver.carve(&mut chunk);
+        chunk.update_surface();
+
+        ores::place_ores(&mut chunk, seed.derive(SALT_ORES));
+
+        chunk.apply_structures(selector, sampler, seed, spill);
+        chunk.compute_light();
 
-        let mut chunk = Chunk {
-            coord: IVec2::new(chunk_x, chunk_z),
+        chunk
+    }
+
+    // all air, nothing generated or lit yet
+    pub fn empty(coord: IVec2) -> Chunk {
+        Chunk {
+            coord,
             blocks: [BlockType::Air; CHUNK_VOLUME],
+            sky_light: [0; CHUNK_VOLUME],
+            block_light: [0; CHUNK_VOLUME],
             surface: [0; CHUNK_SIZE * CHUNK_SIZE],
-        };
+        }
+    }
+
+    fn generate_terrain(
+        chunk_x: i32,
+        chunk_z: i32,
+        selector: &BiomeSelector,
+        sampler: &ClimateSampler,
+        noise: &TerrainNoise,
+        seed: &WorldSeed,
+    ) -> Chunk {
+        let scale = 0.01;
+        let layer_seed = seed.derive(SALT_LAYERS);
+
+        let mut chunk = Chunk::empty(IVec2::new(chunk_x, chunk_z));
 
         for block_x in 0..CHUNK_SIZE as i32 {
             for block_z in 0..CHUNK_SIZE as i32 {
                 let world_x = block_x + chunk_x * CHUNK_SIZE as i32;
                 let world_z = block_z + chunk_z * CHUNK_SIZE as i32;
 
-                let height = FBM.get([world_x as f64 * scale, world_z as f64 * scale]);
+                let height = noise
+                    .height
+                    .get([world_x as f64 * scale, world_z as f64 * scale]);
                 let height = (height + 1.0) * 0.5;
                 let height = (height * (CHUNK_HEIGHT as f64)) as i32;
 
@@ -203,16 +669,55 @@ impl Chunk {
 
                 let height = height.clamp(0, (CHUNK_HEIGHT - 1) as i32) as i32;
 
-                for y in 0..height.max(WATER_HEIGHT as i32) {
+                let weights = selector.blend_weights(&climate_sample);
+                let cliffiness = Self::cliffiness(noise, world_x, world_z);
+
+                let mut solid = [false; CHUNK_HEIGHT];
+                for (y, solid) in solid.iter_mut().enumerate() {
+                    let pos = IVec3::new(world_x, y as i32, world_z);
+
+                    *solid = y == 0
+                        || Self::density(
+                            selector,
+                            noise,
+                            &weights,
+                            &climate_sample,
+                            pos,
+                            height,
+                            cliffiness,
+                        ) > 0.0;
+                }
+
+                let layers = if climate_sample.river > RIVER_BANK_THRESHOLD {
+                    RIVER_BANK_LAYERS
+                } else {
+                    biome.surface_layers()
+                };
+
+                let frozen = biome.water_freezes();
+
+                // how far below the closest exposed block above we are
+                let mut depth = 0;
+
+                for y in (0..CHUNK_HEIGHT as i32).rev() {
+                    let pos = IVec3::new(world_x, y, world_z);
+
                     let block = if y == 0 {
                         BlockType::Bedrock
-                    } else if y < WATER_HEIGHT as i32 {
-                        chunk.surface[(block_x as usize) + (block_z as usize) * CHUNK_SIZE] = -1;
-                        BlockType::Water
+                    } else if solid[y as usize] {
+                        let block = layers.block_at(depth, pos, layer_seed);
+                        depth += 1;
+                        block
                     } else {
-                        chunk.surface[(block_x as usize) + (block_z as usize) * CHUNK_SIZE] =
-                            height;
-                        biome.ground_block()
+                        depth = 0;
+
+                        if frozen && y == WATER_HEIGHT as i32 - 1 {
+                            BlockType::Ice
+                        } else if y < WATER_HEIGHT as i32 {
+                            BlockType::Water
+                        } else {
+                            continue;
+                        }
                     };
 
                     chunk.blocks[Self::to_index(IVec3::new(block_x, y, block_z))] = block;
@@ -223,11 +728,9 @@ impl Chunk {
         chunk
     }
 
-    pub fn new_entity(commands: &mut Commands, material: &ChunkMaterial, coord: IVec2) -> Entity {
-        let chunk = Chunk::new(coord.x, coord.y);
-
-        let world_x = coord.x * CHUNK_SIZE as i32;
-        let world_z = coord.y * CHUNK_SIZE as i32;
+    pub fn new_entity(commands: &mut Commands, material: &ChunkMaterial, chunk: Chunk) -> Entity {
+        let world_x = chunk.coord.x * CHUNK_SIZE as i32;
+        let world_z = chunk.coord.y * CHUNK_SIZE as i32;
 
         commands
             .spawn((
@@ -241,6 +744,55 @@ impl Chunk {
 
 // Helper Functions
 impl Chunk {
+    // > 0 is solid. the heightmap gives the base gradient, 3d noise and the biomes bend it
+    fn density(
+        selector: &BiomeSelector,
+        noise: &TerrainNoise,
+        weights: &[f64],
+        climate: &ClimateSample,
+        pos: IVec3,
+        height: i32,
+        cliffiness: f64,
+    ) -> f64 {
+        let gradient = (height - pos.y) as f64;
+
+        if gradient > DENSITY_BAND as f64 {
+            return 1.0;
+        }
+        if gradient < -DENSITY_BAND as f64 {
+            return -1.0;
+        }
+
+        const SCALE_XZ: f64 = 0.025;
+        const SCALE_Y: f64 = 0.04;
+
+        let n = noise.density.get([
+            pos.x as f64 * SCALE_XZ,
+            pos.y as f64 * SCALE_Y,
+            pos.z as f64 * SCALE_XZ,
+        ]);
+
+        // river valleys stay heightmap shaped so nothing grows back over the water
+        let calm = 1.0 - climate.river;
+
+        gradient
+            + (n * DENSITY_AMPLITUDE * cliffiness
+                + selector.blended_density_offset(weights, pos, climate))
+                * calm
+    }
+
+    fn cliffiness(noise: &TerrainNoise, world_x: i32, world_z: i32) -> f64 {
+        const SCALE: f64 = 0.004;
+
+        let n = noise
+            .cliff
+            .get([world_x as f64 * SCALE, world_z as f64 * SCALE]);
+        let n = ((n + 1.0) * 0.5).clamp(0.0, 1.0);
+
+        // squared so most of the world stays calm and cliffs cluster in regions
+        n * n
+    }
+
     #[inline(always)]
     pub fn to_index(v: IVec3) -> usize {
         v.y as usize + v.z as usize * CHUNK_HEIGHT + v.x as usize * CHUNK_HEIGHT * CHUNK_SIZE
@@ -259,6 +811,13 @@ impl Chunk {
         Some(self.blocks[Self::to_index(local)])
     }
 
+    pub fn set_local(&mut self, local: IVec3, block_type: BlockType) {
+        if self.get_local(local).is_none() {
+            return;
+        }
+        self.blocks[Self::to_index(local)] = block_type;
+    }
+
     pub fn chunk_origin(&self) -> IVec3 {
         IVec3::new(
             self.coord.x * CHUNK_SIZE as i32,
@@ -268,9 +827,171 @@ impl Chunk {
     }
 
     #[inline(always)]
-    fn pad_index(x: i32, y: i32, z: i32) -> usize {
+    pub(super) fn pad_index(x: i32, y: i32, z: i32) -> usize {
         (x as usize)
             + (z as usize) * PAD_CHUNK_SIZE
             + (y as usize) * PAD_CHUNK_SIZE * PAD_CHUNK_SIZE
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::platform::collections::HashMap;
+
+    use super::*;
+
+    type Surface = HashMap<(usize, BlockTextureId, Option<BlockTextureId>), i32>;
+
+    // stone and dirt steps going up along x and z with grass and sand on top,
+    // and a slab hanging over part of it so the light and ao aren't even
+    fn stepped_chunk() -> Chunk {
+        let mut chunk = Chunk::empty(IVec2::ZERO);
+
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                let height = 20 + x / 4 + z / 5;
+
+                for y in 0..=height {
+                    let block = if y == height && x < 10 {
+                        BlockType::Grass
+                    } else if y == height {
+                        BlockType::Sand
+                    } else if y > height - 3 || (x * 3 + y) % 7 == 0 {
+                        BlockType::Dirt
+                    } else {
+                        BlockType::Stone
+                    };
+
+                    chunk.set_local(IVec3::new(x, y, z), block);
+                }
+            }
+        }
+
+        for x in 4..12 {
+            for z in 4..12 {
+                chunk.set_local(IVec3::new(x, 40, z), BlockType::Stone);
+            }
+        }
+
+        chunk.compute_light();
+        chunk
+    }
+
+    // covered area per face direction and texture, and how many quads it took
+    fn surface(
+        chunk: &Chunk,
+        tints: &ChunkTints,
+        mesher: ChunkMesher,
+        lighting: LightingMode,
+    ) -> (Surface, usize) {
+        let padded = chunk.padded(&chunk.blocks, BlockType::Air, |_| None);
+        let light: PaddedLight =
+            LIGHT_KINDS.map(|kind| chunk.padded(chunk.light(kind), 0, |_| None));
+
+        let mut area = Surface::new();
+        let mut quads = 0;
+
+        let mut emit = |face: VisibleFace, dir: FaceDirection, min: IVec3, max: IVec3| {
+            let size = max - min + IVec3::ONE;
+            *area
+                .entry((dir as usize, face.texture_id, face.overlay))
+                .or_default() += size.x * size.y * size.z;
+            quads += 1;
+        };
+
+        match mesher {
+            ChunkMesher::Naive => Chunk::build_naive_mesh(&padded, &light, lighting, &mut emit),
+            ChunkMesher::Greedy => {
+                Chunk::build_greedy_mesh(&padded, &light, tints, lighting, &mut emit)
+            }
+        }
+
+        (area, quads)
+    }
+
+    // plains grass up to x = 8, then a darker biome
+    fn border_tints() -> ChunkTints {
+        ChunkTints::from_columns(|column| {
+            if column.x < 8 {
+                Vec3::new(0.3, 0.5, 0.1)
+            } else {
+                Vec3::new(0.1, 0.3, 0.05)
+            }
+        })
+    }
+
+    #[test]
+    fn greedy_covers_the_same_surface_as_naive() {
+        let chunk = stepped_chunk();
+        let tints = border_tints();
+
+        for lighting in [LightingMode::Flat, LightingMode::Smooth] {
+            let (naive, naive_quads) = surface(&chunk, &tints, ChunkMesher::Naive, lighting);
+            let (greedy, greedy_quads) = surface(&chunk, &tints, ChunkMesher::Greedy, lighting);
+
+            assert_eq!(naive, greedy, "{lighting:?}");
+
+            // faces got merged, but not every texture down to a single quad per direction
+            assert!(greedy_quads < naive_quads, "{lighting:?}");
+            assert!(greedy_quads > greedy.len(), "{lighting:?}");
+        }
+    }
+
+    #[test]
+    fn greedy_keeps_tinted_quads_inside_one_tint() {
+        let mut chunk = Chunk::empty(IVec2::ZERO);
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                chunk.set_local(IVec3::new(x, 0, z), BlockType::Grass);
+            }
+        }
+        chunk.compute_light();
+
+        let tints = border_tints();
+        let padded = chunk.padded(&chunk.blocks, BlockType::Air, |_| None);
+        let light: PaddedLight =
+            LIGHT_KINDS.map(|kind| chunk.padded(chunk.light(kind), 0, |_| None));
+
+        let mut tops = Vec::new();
+        let mut emit = |face: VisibleFace, dir: FaceDirection, min: IVec3, max: IVec3| {
+            if matches!(dir, FaceDirection::Top) {
+                tops.push((face, min, max));
+            }
+        };
+
+        Chunk::build_greedy_mesh(&padded, &light, &tints, LightingMode::Smooth, &mut emit);
+
+        // every block a quad covers is colored like the quad's corners
+        for (face, min, max) in &tops {
+            let quad = Chunk::quad_tint(
+                &tints,
+                face.texture_id.tint(),
+                FaceDirection::Top,
+                *min,
+                *max,
+            );
+
+            for x in min.x..=max.x {
+                for z in min.z..=max.z {
+                    let pos = IVec3::new(x, 0, z);
+                    let block = Chunk::quad_tint(
+                        &tints,
+                        face.texture_id.tint(),
+                        FaceDirection::Top,
+                        pos,
+                        pos,
+                    );
+                    assert_eq!(block, quad, "{min} {max} at {pos}");
+                }
+            }
+        }
+
+        // both biomes still merge on their own side, the blend between them doesn't
+        assert!(tops.len() < CHUNK_SIZE * CHUNK_SIZE);
+        assert!(
+            tops.iter()
+                .any(|(_, min, max)| min.x < 8 && max.x > 0 && max.z > min.z)
+        );
+        assert!(tops.iter().all(|(_, min, max)| min.x >= 8 || max.x < 8));
+    }
+}
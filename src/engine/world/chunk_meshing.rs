@@ -1,22 +1,36 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use bevy::ecs::change_detection::Tick;
 use bevy::ecs::system::SystemParam;
+use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on};
+use futures_lite::future;
 
+use super::biome_tint::{BiomeColormaps, ChunkTints, load_colormaps};
 use super::block::*;
 use super::chunk::CHUNK_SIZE;
 use super::chunk::*;
-use crate::engine::atlas::BlockAtlas;
+use super::generator::SharedWorldGenerator;
+use super::light::{LIGHT_KINDS, LightKind, LightRead, LightUpdates, MAX_LIGHT};
+use super::pending_writes::{PendingBlockWrites, chunks_touching, split_world_pos};
+use crate::engine::atlas::{BlockAtlas, TranslucentChunkMaterial};
 
 // reusable access pattern for ecs bevy data
 #[derive(SystemParam)]
 pub struct WorldBlockReadAccess<'w, 's> {
-    chunks: Query<'w, 's, &'static Chunk>,
+    chunks: Query<'w, 's, Ref<'static, Chunk>>,
     map: Res<'w, ChunkMap>,
 }
 
 #[derive(SystemParam)]
 pub struct WorldBlockWriteAccess<'w, 's> {
+    commands: Commands<'w, 's>,
     chunks: Query<'w, 's, &'static mut Chunk>,
     map: Res<'w, ChunkMap>,
+    pending: ResMut<'w, PendingBlockWrites>,
+    light_updates: ResMut<'w, LightUpdates>,
 }
 
 pub struct ChunkMeshingPlugin;
@@ -24,8 +38,177 @@ pub struct ChunkMeshingPlugin;
 #[derive(Component)]
 pub struct UnmeshedChunk;
 
+// child of a chunk holding its translucent mesh, an entity only renders one mesh
+#[derive(Component)]
+pub struct TranslucentChunkMesh;
+
+// chunk already has a mesh but its blocks changed, keeps the old mesh visible until the new one lands
+#[derive(Component)]
+pub struct RemeshChunk;
+
+// main thread time per frame spent snapshotting chunks and uploading finished meshes
+#[derive(Resource)]
+pub struct ChunkMeshingBudget(pub Duration);
+
+// which mesher new chunk meshes are built with. switching it remeshes every loaded chunk
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChunkMesher {
+    // one quad per visible face
+    Naive,
+    // coplanar faces with the same texture merged into larger quads
+    #[default]
+    Greedy,
+}
+
+// how light is spread over a face. switching it remeshes every loaded chunk
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LightingMode {
+    // every face takes the light of the block in front of it
+    Flat,
+    // each corner averages the blocks in front of the face that touch it
+    #[default]
+    Smooth,
+}
+
+#[derive(SystemParam)]
+struct MeshingSettings<'w> {
+    atlas: Res<'w, BlockAtlas>,
+    mesher: Res<'w, ChunkMesher>,
+    lighting: Res<'w, LightingMode>,
+    budget: Res<'w, ChunkMeshingBudget>,
+    generator: Res<'w, SharedWorldGenerator>,
+    colormaps: Res<'w, BiomeColormaps>,
+}
+
+struct MeshingTask {
+    task: Task<ChunkMeshes>,
+    // change ticks of the chunk and its neighbours when it was snapshotted,
+    // anything newer makes the result stale
+    snapshot_ticks: Vec<(Entity, Tick)>,
+}
+
+#[derive(Resource, Default)]
+struct MeshingTasks(HashMap<Entity, MeshingTask>);
+
+struct FinishedMesh {
+    entity: Entity,
+    snapshot_ticks: Vec<(Entity, Tick)>,
+    meshes: ChunkMeshes,
+}
+
+// meshes whose task is done but that didn't fit into the frame budget yet.
+// a finished task can't be polled again, so they wait here instead of in MeshingTasks
 #[derive(Resource, Default)]
-pub struct ChunkMeshingBudget(usize);
+struct FinishedMeshes(VecDeque<FinishedMesh>);
+
+// owned copy of a chunk and the blocks and light of its eight neighbours that touch it,
+// enough to mesh it without access to the ecs
+pub struct ChunkMeshSnapshot {
+    chunk: Chunk,
+    // one per NEIGHBOUR_OFFSETS entry, the neighbour's blocks along the shared edge.
+    // indexed by position along the edge * CHUNK_HEIGHT + y, corners only have one column
+    borders: [Vec<BlockType>; 8],
+    // light of the same blocks per kind, same layout
+    light_borders: [[Vec<u8>; 8]; LIGHT_KINDS.len()],
+}
+
+const NEIGHBOUR_OFFSETS: [IVec2; 8] = [
+    IVec2::NEG_X,
+    IVec2::X,
+    IVec2::NEG_Y,
+    IVec2::Y,
+    IVec2::new(-1, -1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(1, 1),
+];
+
+impl ChunkMeshSnapshot {
+    // None while any of the neighbours hasn't been generated yet
+    fn capture(chunk: &Chunk, access: &WorldBlockReadAccess) -> Option<ChunkMeshSnapshot> {
+        let mut borders: [Vec<BlockType>; 8] = Default::default();
+        let mut light_borders: [[Vec<u8>; 8]; LIGHT_KINDS.len()] = Default::default();
+
+        for (i, offset) in NEIGHBOUR_OFFSETS.into_iter().enumerate() {
+            let entity = access.map.0.get(&(chunk.coord + offset))?;
+            let neighbour = access.chunks.get(*entity).ok()?;
+
+            for (x, z) in Self::edge_columns(offset) {
+                for y in 0..CHUNK_HEIGHT as i32 {
+                    let index = Chunk::to_index(IVec3::new(x, y, z));
+                    borders[i].push(neighbour.blocks[index]);
+
+                    for kind in LIGHT_KINDS {
+                        light_borders[kind as usize][i].push(neighbour.light(kind)[index]);
+                    }
+                }
+            }
+        }
+
+        Some(ChunkMeshSnapshot {
+            chunk: chunk.clone(),
+            borders,
+            light_borders,
+        })
+    }
+
+    // local columns of the neighbour at `offset` that face us, in storage order
+    fn edge_columns(offset: IVec2) -> impl Iterator<Item = (i32, i32)> {
+        let size = CHUNK_SIZE as i32;
+        let range = |o: i32| match o {
+            -1 => size - 1..size,
+            1 => 0..1,
+            _ => 0..size,
+        };
+
+        let xs = range(offset.x);
+        range(offset.y).flat_map(move |z| xs.clone().map(move |x| (x, z)))
+    }
+
+    // `own` and `borders` are the chunk's and the neighbours' copies of the same per block data
+    fn lookup<T: Copy>(&self, world: IVec3, own: &[T], borders: &[Vec<T>; 8]) -> Option<T> {
+        let local = world - self.chunk.chunk_origin();
+        let size = CHUNK_SIZE as i32;
+
+        if local.y < 0 || local.y >= CHUNK_HEIGHT as i32 {
+            return None;
+        }
+
+        let offset = IVec2::new(local.x.div_euclid(size), local.z.div_euclid(size));
+        if offset == IVec2::ZERO {
+            return Some(own[Chunk::to_index(local)]);
+        }
+
+        let border = NEIGHBOUR_OFFSETS.iter().position(|o| *o == offset)?;
+        let (x, z) = (local.x.rem_euclid(size), local.z.rem_euclid(size));
+
+        // only the row right next to us was captured
+        let column = Self::edge_columns(offset).position(|column| column == (x, z))?;
+
+        Some(borders[border][column * CHUNK_HEIGHT + local.y as usize])
+    }
+}
+
+impl BlockRead for ChunkMeshSnapshot {
+    fn get_block(&self, world: IVec3) -> Option<BlockType> {
+        self.lookup(world, &self.chunk.blocks, &self.borders)
+    }
+}
+
+impl LightRead for ChunkMeshSnapshot {
+    fn get_light(&self, world: IVec3, kind: LightKind) -> Option<u8> {
+        // nothing above the world blocks the sky
+        if world.y >= CHUNK_HEIGHT as i32 && kind == LightKind::Sky {
+            return Some(MAX_LIGHT);
+        }
+
+        self.lookup(
+            world,
+            self.chunk.light(kind),
+            &self.light_borders[kind as usize],
+        )
+    }
+}
 
 impl BlockRead for WorldBlockReadAccess<'_, '_> {
     fn get_block(&self, world: IVec3) -> Option<BlockType> {
@@ -49,21 +232,27 @@ impl BlockRead for WorldBlockReadAccess<'_, '_> {
 
 impl BlockWrite for WorldBlockWriteAccess<'_, '_> {
     fn set_block(&mut self, world: IVec3, block_type: BlockType) {
-        let chunk_coord = IVec2::new(
-            world.x.div_euclid(CHUNK_SIZE as i32),
-            world.z.div_euclid(CHUNK_SIZE as i32),
-        );
+        let (chunk_coord, local) = split_world_pos(world);
 
-        let local = IVec3::new(
-            world.x.rem_euclid(CHUNK_SIZE as i32),
-            world.y,
-            world.z.rem_euclid(CHUNK_SIZE as i32),
-        );
+        let loaded = self
+            .map
+            .0
+            .get(&chunk_coord)
+            .and_then(|entity| self.chunks.get_mut(*entity).ok());
+
+        match loaded {
+            Some(mut chunk) => {
+                chunk.set_local(local, block_type);
+                self.light_updates.block_changed(world);
 
-        if let Some(entity) = self.map.0.get(&chunk_coord) {
-            if let Ok(mut chunk) = self.chunks.get_mut(*entity) {
-                chunk.blocks[Chunk::to_index(local)] = block_type;
+                for coord in chunks_touching(chunk_coord, local) {
+                    if let Some(entity) = self.map.0.get(&coord) {
+                        self.commands.entity(*entity).insert(RemeshChunk);
+                    }
+                }
             }
+            // chunk isn't there yet, keep the write around until it spawns
+            None => self.pending.push(world, block_type),
         }
     }
 }
@@ -71,25 +260,269 @@ impl BlockWrite for WorldBlockWriteAccess<'_, '_> {
 impl Plugin for ChunkMeshingPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ChunkMap>();
-        app.insert_resource(ChunkMeshingBudget(100));
+        app.init_resource::<PendingBlockWrites>();
+        app.init_resource::<LightUpdates>();
+        app.insert_resource(ChunkMeshingBudget(Duration::from_millis(4)));
+        app.init_resource::<ChunkMesher>();
+        app.init_resource::<LightingMode>();
+        app.init_resource::<MeshingTasks>();
+        app.init_resource::<FinishedMeshes>();
+
+        app.add_systems(Startup, load_colormaps);
+        app.add_systems(
+            Update,
+            (
+                remesh_all_chunks
+                    .run_if(resource_changed::<ChunkMesher>.or(resource_changed::<LightingMode>)),
+                upload_meshes.pipe(mesh_chunks).run_if(
+                    resource_exists::<BlockAtlas>.and(resource_exists::<SharedWorldGenerator>),
+                ),
+            )
+                .chain(),
+        );
+    }
+}
+
+fn change_ticks(coord: IVec2, access: &WorldBlockReadAccess) -> Vec<(Entity, Tick)> {
+    std::iter::once(IVec2::ZERO)
+        .chain(NEIGHBOUR_OFFSETS)
+        .filter_map(|offset| {
+            let entity = *access.map.0.get(&(coord + offset))?;
+            Some((entity, access.chunks.get(entity).ok()?.last_changed()))
+        })
+        .collect()
+}
+
+fn remesh_all_chunks(
+    mut commands: Commands,
+    mut tasks: ResMut<MeshingTasks>,
+    mut finished: ResMut<FinishedMeshes>,
+    meshed: Query<Entity, (With<Chunk>, With<Mesh3d>)>,
+) {
+    // in flight and waiting meshes were built with the old settings
+    tasks.0.clear();
+    finished.0.clear();
 
-        app.add_systems(Update, mesh_chunks.run_if(resource_exists::<BlockAtlas>));
+    for entity in meshed.iter() {
+        commands.entity(entity).insert(RemeshChunk);
     }
 }
 
-fn mesh_chunks(
+// finished meshes go first, so a tight budget still makes progress. hands mesh_chunks the
+// time the frame's meshing started, or None once the budget is used up
+fn upload_meshes(
     mut commands: Commands,
     access: WorldBlockReadAccess,
-    atlas: Res<BlockAtlas>,
-    mut mesh_assets: ResMut<Assets<Mesh>>,
-    query: Query<(Entity, &Chunk), (Without<Mesh3d>, Without<UnmeshedChunk>)>,
     budget: Res<ChunkMeshingBudget>,
+    mut mesh_assets: ResMut<Assets<Mesh>>,
+    translucent_material: Res<TranslucentChunkMaterial>,
+    mut tasks: ResMut<MeshingTasks>,
+    mut finished: ResMut<FinishedMeshes>,
+) -> Option<Instant> {
+    let start = Instant::now();
+
+    tasks.0.retain(|entity, in_flight| {
+        let Some(meshes) = block_on(future::poll_once(&mut in_flight.task)) else {
+            return true;
+        };
+
+        finished.0.push_back(FinishedMesh {
+            entity: *entity,
+            snapshot_ticks: std::mem::take(&mut in_flight.snapshot_ticks),
+            meshes,
+        });
+
+        false
+    });
+
+    while let Some(FinishedMesh {
+        entity,
+        snapshot_ticks,
+        meshes,
+    }) = finished.0.pop_front()
+    {
+        // chunk got despawned while we were busy
+        if !access.chunks.contains(entity) {
+            continue;
+        }
+
+        // the snapshot also copied the neighbours' border blocks and light, a neighbour that
+        // went away since doesn't matter, the faces towards it were built already
+        let stale = snapshot_ticks.iter().any(|(entity, tick)| {
+            access
+                .chunks
+                .get(*entity)
+                .is_ok_and(|chunk| chunk.last_changed() != *tick)
+        });
+
+        if stale {
+            commands.entity(entity).insert(RemeshChunk);
+            continue;
+        }
+
+        let mut chunk_commands = commands.entity(entity);
+
+        chunk_commands
+            .insert(Mesh3d(mesh_assets.add(meshes.opaque)))
+            .remove::<RemeshChunk>()
+            .despawn_related::<Children>();
+
+        if let Some(translucent) = meshes.translucent {
+            chunk_commands.with_child((
+                TranslucentChunkMesh,
+                Mesh3d(mesh_assets.add(translucent)),
+                MeshMaterial3d(translucent_material.0.clone()),
+            ));
+        }
+
+        if start.elapsed() >= budget.0 {
+            return None;
+        }
+    }
+
+    Some(start)
+}
+
+fn mesh_chunks(
+    In(start): In<Option<Instant>>,
+    access: WorldBlockReadAccess,
+    settings: MeshingSettings,
+    mut tasks: ResMut<MeshingTasks>,
+    query: Query<
+        (Entity, Ref<Chunk>),
+        (
+            Or<(Without<Mesh3d>, With<RemeshChunk>)>,
+            Without<UnmeshedChunk>,
+        ),
+    >,
 ) {
-    for (entity, chunk) in query.iter().take(budget.0) {
-        let mesh = chunk.build_chunk_mesh(&access, &atlas.texture);
+    // uploads used up the frame, chunks with a mesh still waiting aren't snapshotted twice either
+    let Some(start) = start else {
+        return;
+    };
+    let budget = settings.budget.0;
+
+    let pool = AsyncComputeTaskPool::get();
+
+    for (entity, chunk) in query.iter() {
+        if start.elapsed() >= budget {
+            break;
+        }
+
+        if tasks.0.contains_key(&entity) {
+            continue;
+        }
+
+        let Some(snapshot) = ChunkMeshSnapshot::capture(&chunk, &access) else {
+            continue;
+        };
+
+        let atlas = settings.atlas.texture.clone();
+        let mesher = *settings.mesher;
+        let lighting = *settings.lighting;
+        let generator = settings.generator.clone();
+        let colormaps = settings.colormaps.clone();
+
+        let task = pool.spawn(async move {
+            let tints = ChunkTints::new(snapshot.chunk.coord, &generator.sampler, &colormaps);
+            snapshot
+                .chunk
+                .build_chunk_mesh(&snapshot, &atlas, &tints, mesher, lighting)
+        });
+
+        tasks.0.insert(
+            entity,
+            MeshingTask {
+                task,
+                snapshot_ticks: change_ticks(chunk.coord, &access),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::RenderAssetUsages;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::mesh::PrimitiveTopology;
+    use bevy::tasks::TaskPool;
+
+    use super::*;
+
+    const CHUNKS: i32 = 3;
+
+    // chunks whose meshing tasks have all finished, and a budget that only fits one upload
+    fn world() -> World {
+        let mut world = World::new();
+        let pool = AsyncComputeTaskPool::get_or_init(TaskPool::new);
+
+        let mut map = ChunkMap::default();
+        let mut tasks = MeshingTasks::default();
+
+        for x in 0..CHUNKS {
+            let coord = IVec2::new(x, 0);
+            let entity = world.spawn(Chunk::empty(coord)).id();
+            map.0.insert(coord, entity);
+
+            let task = pool.spawn(async {
+                ChunkMeshes {
+                    opaque: Mesh::new(
+                        PrimitiveTopology::TriangleList,
+                        RenderAssetUsages::default(),
+                    ),
+                    translucent: None,
+                }
+            });
+            while !task.is_finished() {
+                std::thread::yield_now();
+            }
+
+            tasks.0.insert(
+                entity,
+                MeshingTask {
+                    task,
+                    snapshot_ticks: Vec::new(),
+                },
+            );
+        }
+
+        world.insert_resource(map);
+        world.insert_resource(tasks);
+        world.init_resource::<FinishedMeshes>();
+        world.init_resource::<Assets<Mesh>>();
+        world.insert_resource(TranslucentChunkMaterial(Handle::default()));
+        world.insert_resource(ChunkMeshingBudget(Duration::ZERO));
+
+        world
+    }
+
+    fn meshed(world: &mut World) -> usize {
+        world
+            .query_filtered::<(), (With<Chunk>, With<Mesh3d>)>()
+            .iter(world)
+            .count()
+    }
+
+    #[test]
+    fn finished_meshes_over_budget_wait_for_the_next_frame() {
+        let mut world = world();
+
+        let start = world.run_system_once(upload_meshes).unwrap();
+        assert!(start.is_none());
+        assert_eq!(meshed(&mut world), 1);
+        assert!(world.resource::<MeshingTasks>().0.is_empty());
+        assert_eq!(
+            world.resource::<FinishedMeshes>().0.len(),
+            CHUNKS as usize - 1
+        );
+
+        // the finished tasks are gone, nothing gets polled a second time
+        for frame in 2..=CHUNKS as usize {
+            world.run_system_once(upload_meshes).unwrap();
+            assert_eq!(meshed(&mut world), frame);
+        }
 
-        commands
-            .entity(entity)
-            .insert(Mesh3d(mesh_assets.add(mesh)));
+        assert!(world.resource::<FinishedMeshes>().0.is_empty());
+        assert!(world.run_system_once(upload_meshes).unwrap().is_some());
     }
 }
@@ -1,35 +1,49 @@
 use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
 
-const SEED_TEMPERATURE: u32 = 42;
-const SEED_MOISTURE: u32 = 73;
+use super::seed::{SALT_MOISTURE, SALT_RIVER, SALT_RIVER_WIDTH, SALT_TEMPERATURE, WorldSeed};
 
 pub struct ClimateSample {
     pub temperature: f64,
     pub moisture: f64,
+    // 1.0 inside a river channel, fading to 0.0 at the edge of its valley
+    pub river: f64,
 }
 
 pub struct ClimateSampler {
     scale: f64,
     temperature_gen: Fbm<Perlin>,
     moisture_gen: Fbm<Perlin>,
+    river_scale: f64,
+    river_gen: Fbm<Perlin>,
+    river_width_gen: Perlin,
 }
 
 impl ClimateSampler {
-    pub fn new() -> ClimateSampler {
+    pub fn new(seed: &WorldSeed) -> ClimateSampler {
         ClimateSampler {
-            temperature_gen: Fbm::<Perlin>::new(SEED_TEMPERATURE)
+            temperature_gen: Fbm::<Perlin>::new(seed.derive(SALT_TEMPERATURE))
                 .set_frequency(0.5)
                 .set_octaves(2)
                 .set_lacunarity(2.0)
                 .set_persistence(0.8),
 
-            moisture_gen: Fbm::<Perlin>::new(SEED_MOISTURE)
+            moisture_gen: Fbm::<Perlin>::new(seed.derive(SALT_MOISTURE))
                 .set_frequency(0.7)
                 .set_octaves(2)
                 .set_lacunarity(1.4)
                 .set_persistence(0.8),
 
             scale: 0.002,
+
+            river_gen: Fbm::<Perlin>::new(seed.derive(SALT_RIVER))
+                .set_frequency(0.5)
+                .set_octaves(2)
+                .set_lacunarity(2.0)
+                .set_persistence(0.4),
+
+            river_width_gen: Perlin::new(seed.derive(SALT_RIVER_WIDTH)),
+
+            river_scale: 0.0015,
         }
     }
 }
@@ -43,6 +57,29 @@ impl ClimateSampler {
         ClimateSample {
             temperature,
             moisture,
+            river: self.sample_river(world_x, world_z),
         }
     }
+
+    // rivers follow the zero line of the river noise, which always forms connected curves
+    fn sample_river(&self, world_x: i32, world_z: i32) -> f64 {
+        const VALLEY_FACTOR: f64 = 3.0;
+
+        let coord = [
+            world_x as f64 * self.river_scale,
+            world_z as f64 * self.river_scale,
+        ];
+
+        let distance = self.river_gen.get(coord).abs();
+
+        let width = self.river_width_gen.get([coord[0] * 2.0, coord[1] * 2.0]);
+        let width = 0.01 + 0.015 * (width + 1.0) * 0.5;
+
+        1.0 - smoothstep(width, width * VALLEY_FACTOR, distance)
+    }
+}
+
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
 }
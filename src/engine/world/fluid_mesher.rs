@@ -0,0 +1,133 @@
+use bevy::color::{Alpha, Color, ColorToComponents, LinearRgba};
+use bevy::math::IVec3;
+
+use super::block::{BlockType, Opacity};
+use super::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunk, PAD_CHUNK_VOLUME, PaddedLight};
+use super::light::{self, LightKind};
+use crate::engine::atlas::TextureAtlas;
+use crate::engine::face_direction::{DIRECTIONS, FACE_NORMALS, FACE_VERTICES, FaceDirection};
+use crate::engine::mesh_builder::MeshBuilder;
+
+// surface of a fluid with open air above it sits a bit below the block top
+pub const SURFACE_HEIGHT: f32 = 7.0 / 8.0;
+
+// the water texture is grayscale, this is minecraft's default water color
+const WATER_COLOR: Color = Color::srgb(0.247, 0.463, 0.894);
+
+// fluids aren't cubes, so they skip the block meshers and get their faces from here.
+// tops are lowered where they meet air and slope towards lower neighbours, faces between
+// two blocks of the same fluid are left out
+pub fn build_fluid_mesh(
+    padded: &[BlockType; PAD_CHUNK_VOLUME],
+    light: &PaddedLight,
+    atlas: &TextureAtlas,
+    builder: &mut MeshBuilder,
+) {
+    let at = |p: IVec3| padded[Chunk::pad_index(p.x, p.y, p.z)];
+    let water = LinearRgba::from(WATER_COLOR);
+
+    for y in 0..CHUNK_HEIGHT as i32 {
+        for z in 0..CHUNK_SIZE as i32 {
+            for x in 0..CHUNK_SIZE as i32 {
+                let pos = IVec3::new(x, y, z);
+                let padded_pos = pos + IVec3::ONE;
+                let fluid = at(padded_pos);
+
+                if !fluid.is_fluid() {
+                    continue;
+                }
+
+                let is_surface = at(padded_pos + IVec3::Y) != fluid;
+
+                for dir in DIRECTIONS {
+                    let front = padded_pos + dir.normal();
+                    if !fluid.shows_face_to(at(front)) {
+                        continue;
+                    }
+
+                    let Some(texture_id) = fluid.texture_id(dir) else {
+                        continue;
+                    };
+                    let tile = atlas.tile_origin(texture_id);
+
+                    let index = Chunk::pad_index(front.x, front.y, front.z);
+                    let (brightness, block_share) = light::vertex_light(
+                        light[LightKind::Sky as usize][index] as f32,
+                        light[LightKind::Block as usize][index] as f32,
+                    );
+                    let color = (water * brightness).with_alpha(block_share).to_f32_array();
+
+                    let corners = FACE_VERTICES[dir as usize].map(|corner| {
+                        let top = if is_surface {
+                            corner_height(padded, fluid, padded_pos, corner)
+                        } else {
+                            1.0
+                        };
+
+                        [
+                            pos.x as f32 + corner[0],
+                            pos.y as f32 - 0.5 + if corner[1] > 0.0 { top } else { 0.0 },
+                            pos.z as f32 + corner[2],
+                        ]
+                    });
+
+                    builder.add_shaped_quad(corners, FACE_NORMALS[dir as usize], tile, color);
+
+                    // the surface seen from below
+                    if matches!(dir, FaceDirection::Top) {
+                        let [a, b, c, d] = corners;
+                        let normal = FACE_NORMALS[FaceDirection::Bottom as usize];
+                        builder.add_shaped_quad([d, c, b, a], normal, tile, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// height of the top corner of `padded_pos` at `corner` (a FACE_VERTICES entry), averaged over
+// the four columns sharing it. fluid with more of itself on top fills the corner, air pulls
+// it down, solid blocks don't have a say
+fn corner_height(
+    padded: &[BlockType; PAD_CHUNK_VOLUME],
+    fluid: BlockType,
+    padded_pos: IVec3,
+    corner: [f32; 3],
+) -> f32 {
+    let at = |p: IVec3| padded[Chunk::pad_index(p.x, p.y, p.z)];
+
+    let sx = if corner[0] < 0.0 { -1 } else { 1 };
+    let sz = if corner[2] < 0.0 { -1 } else { 1 };
+
+    let mut sum = 0.0;
+    let mut count = 0;
+
+    for offset in [
+        IVec3::ZERO,
+        IVec3::new(sx, 0, 0),
+        IVec3::new(0, 0, sz),
+        IVec3::new(sx, 0, sz),
+    ] {
+        let cell = padded_pos + offset;
+        let block = at(cell);
+
+        if block == fluid {
+            let above = at(cell + IVec3::Y);
+
+            if above == fluid {
+                return 1.0;
+            }
+
+            sum += if above.opacity() == Opacity::Empty {
+                SURFACE_HEIGHT
+            } else {
+                1.0
+            };
+            count += 1;
+        } else if block.opacity() == Opacity::Empty {
+            count += 1;
+        }
+    }
+
+    sum / count as f32
+}
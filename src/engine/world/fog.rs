@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use super::block::{BlockRead, BlockType};
+use super::chunk::CHUNK_SIZE;
+use super::chunk_meshing::WorldBlockReadAccess;
+use super::fluid_mesher::SURFACE_HEIGHT;
+use super::streaming::StreamingResource;
+use crate::engine::day_night::WorldTime;
+
+const WATER_FOG_COLOR: Srgba = Srgba::rgb(0.09, 0.22, 0.45);
+
+pub struct FogPlugin {
+    // where the fog starts, as a fraction of the render distance
+    pub start: f32,
+    // blocks it takes underwater until nothing is visible anymore
+    pub underwater_visibility: f32,
+}
+
+#[derive(Resource, Clone)]
+struct FogConfig {
+    start: f32,
+    underwater_visibility: f32,
+}
+
+impl Default for FogPlugin {
+    fn default() -> Self {
+        Self {
+            start: 0.6,
+            underwater_visibility: 24.0,
+        }
+    }
+}
+
+impl Plugin for FogPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FogConfig {
+            start: self.start,
+            underwater_visibility: self.underwater_visibility,
+        })
+        .add_systems(Update, (add_fog, update_fog).chain());
+    }
+}
+
+fn add_fog(mut commands: Commands, cameras: Query<Entity, Added<Camera3d>>) {
+    for entity in cameras.iter() {
+        commands.entity(entity).insert(DistanceFog::default());
+    }
+}
+
+// blocks are centered on their position. the fluid mesher lowers the top of water that
+// has no water above it, the eye has to be below that surface
+fn is_underwater(world: &impl BlockRead, eye: Vec3) -> bool {
+    let block = (eye + Vec3::splat(0.5)).floor().as_ivec3();
+
+    if world.get_block(block) != Some(BlockType::Water) {
+        return false;
+    }
+
+    let bottom = block.y as f32 - 0.5;
+
+    world.get_block(block + IVec3::Y) == Some(BlockType::Water) || eye.y - bottom < SURFACE_HEIGHT
+}
+
+// fades chunks out before the edge of the render distance, in the sky's color so they
+// vanish into it. inside water everything fades into the water instead, a lot closer
+fn update_fog(
+    config: Res<FogConfig>,
+    streaming: Res<StreamingResource>,
+    clear_color: Res<ClearColor>,
+    world_time: Option<Res<WorldTime>>,
+    world: WorldBlockReadAccess,
+    mut cameras: Query<(&GlobalTransform, &mut DistanceFog), With<Camera3d>>,
+) {
+    // the outermost ring of chunks is never meshed, the camera can be anywhere in its own chunk
+    let end = (streaming.render_distance * CHUNK_SIZE) as f32;
+
+    for (transform, mut fog) in cameras.iter_mut() {
+        if is_underwater(&world, transform.translation()) {
+            // don't let the water glow at night
+            let daylight = world_time.as_ref().map_or(1.0, |time| time.daylight());
+
+            fog.color = (WATER_FOG_COLOR * daylight).with_alpha(1.0).into();
+            fog.falloff = FogFalloff::Linear {
+                start: 0.0,
+                end: config.underwater_visibility,
+            };
+        } else {
+            fog.color = clear_color.0;
+            fog.falloff = FogFalloff::Linear {
+                start: end * config.start,
+                end,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::platform::collections::HashMap;
+
+    use super::*;
+
+    struct Blocks(HashMap<IVec3, BlockType>);
+
+    impl BlockRead for Blocks {
+        fn get_block(&self, world_pos: IVec3) -> Option<BlockType> {
+            Some(*self.0.get(&world_pos).unwrap_or(&BlockType::Air))
+        }
+    }
+
+    #[test]
+    fn surface_water_only_counts_below_its_lowered_top() {
+        // water two blocks deep, centered at y = 0 and y = 1
+        let world = Blocks(HashMap::from([
+            (IVec3::ZERO, BlockType::Water),
+            (IVec3::Y, BlockType::Water),
+        ]));
+
+        // the surface block spans 0.5..1.5 and its mesh ends at 0.5 + 7/8
+        assert!(is_underwater(&world, Vec3::new(0.0, 1.3, 0.0)));
+        assert!(!is_underwater(&world, Vec3::new(0.0, 1.4, 0.0)));
+        assert!(!is_underwater(&world, Vec3::new(0.0, 1.6, 0.0)));
+
+        // the block below is full, water above it
+        assert!(is_underwater(&world, Vec3::new(0.0, 0.45, 0.0)));
+        assert!(is_underwater(&world, Vec3::new(0.2, -0.45, -0.3)));
+        assert!(!is_underwater(&world, Vec3::new(0.0, -0.6, 0.0)));
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use super::biome::BiomeSelector;
+use super::caves::CaveCarver;
+use super::chunk::{Chunk, TerrainNoise};
+use super::climate_sampler::ClimateSampler;
+use super::pending_writes::PendingBlockWrites;
+use super::seed::{SALT_CAVES, WorldSeed};
+
+// everything chunk generation needs, built once per seed and shared between chunks
+pub struct WorldGenerator {
+    pub seed: WorldSeed,
+    pub selector: BiomeSelector,
+    pub sampler: ClimateSampler,
+    pub noise: TerrainNoise,
+    pub carver: CaveCarver,
+}
+
+#[derive(Resource, Clone, Deref)]
+pub struct SharedWorldGenerator(pub Arc<WorldGenerator>);
+
+impl WorldGenerator {
+    pub fn new(seed: WorldSeed) -> WorldGenerator {
+        WorldGenerator {
+            seed,
+            selector: BiomeSelector::new(&seed),
+            sampler: ClimateSampler::new(&seed),
+            noise: TerrainNoise::new(&seed),
+            carver: CaveCarver::new(seed.derive(SALT_CAVES)),
+        }
+    }
+
+    // a chunk on its own, whatever its structures write into the neighbours is dropped
+    pub fn generate_chunk(&self, coord: IVec2) -> Chunk {
+        Chunk::new(coord.x, coord.y, self, &mut PendingBlockWrites::default())
+    }
+}
+
+pub fn rebuild_world_generator(mut commands: Commands, seed: Res<WorldSeed>) {
+    commands.insert_resource(SharedWorldGenerator(Arc::new(WorldGenerator::new(*seed))));
+}
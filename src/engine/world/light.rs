@@ -0,0 +1,479 @@
+use std::collections::VecDeque;
+
+use bevy::platform::collections::HashSet;
+use bevy::prelude::*;
+
+use super::block::BlockType;
+use super::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunk, ChunkMap};
+use super::chunk_meshing::RemeshChunk;
+use super::pending_writes::{chunks_touching, split_world_pos};
+use crate::engine::face_direction::{DIRECTIONS, FaceDirection};
+
+pub const MAX_LIGHT: u8 = 15;
+
+// brightness of a block no light reaches at all
+const MIN_BRIGHTNESS: f32 = 0.05;
+
+// light from above the world and light given off by blocks, each spreads on its own
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LightKind {
+    Sky,
+    Block,
+}
+
+pub const LIGHT_KINDS: [LightKind; 2] = [LightKind::Sky, LightKind::Block];
+
+// blocks and chunks whose light hasn't caught up with them yet
+#[derive(Resource, Default)]
+pub struct LightUpdates {
+    blocks: Vec<IVec3>,
+    chunks: Vec<IVec2>,
+}
+
+pub trait LightRead {
+    fn get_light(&self, world_pos: IVec3, kind: LightKind) -> Option<u8>;
+}
+
+// the loaded chunks seen as one light volume, remembers which chunk meshes went stale
+struct LightVolume<'a, 'w, 's, 'c> {
+    chunks: &'a mut Query<'w, 's, &'c mut Chunk>,
+    map: &'a ChunkMap,
+    touched: HashSet<IVec2>,
+}
+
+impl LightUpdates {
+    pub fn block_changed(&mut self, world_pos: IVec3) {
+        self.blocks.push(world_pos);
+    }
+
+    pub fn chunk_spawned(&mut self, coord: IVec2) {
+        self.chunks.push(coord);
+    }
+}
+
+// light `into` gets from a neighbour with `level` in direction `dir`.
+// full sky light keeps going straight down through clear blocks without getting weaker
+fn spread(kind: LightKind, level: u8, dir: FaceDirection, into: BlockType) -> u8 {
+    let opacity = into.light_opacity();
+
+    if kind == LightKind::Sky
+        && level == MAX_LIGHT
+        && opacity == 0
+        && matches!(dir, FaceDirection::Bottom)
+    {
+        MAX_LIGHT
+    } else {
+        level.saturating_sub(opacity.max(1))
+    }
+}
+
+// vertex color multiplier for a light level, falls off like minecraft's light curve.
+// smooth lighting averages levels, so they don't have to be whole
+pub fn brightness(level: f32) -> f32 {
+    let ratio = level / MAX_LIGHT as f32;
+    let curved = ratio / (4.0 - 3.0 * ratio);
+
+    MIN_BRIGHTNESS + (1.0 - MIN_BRIGHTNESS) * curved
+}
+
+// what a vertex keeps of its light: the brightness of the brighter kind and how much of it
+// the block light alone gives. the chunk shader scales the rest with the time of day
+pub fn vertex_light(sky: f32, block: f32) -> (f32, f32) {
+    let sky = brightness(sky);
+    let block = brightness(block);
+    let brightest = sky.max(block);
+
+    (brightest, block / brightest)
+}
+
+impl Chunk {
+    // 0..=MAX_LIGHT per block, same layout as `blocks`
+    pub fn light(&self, kind: LightKind) -> &[u8] {
+        match kind {
+            LightKind::Sky => &self.sky_light,
+            LightKind::Block => &self.block_light,
+        }
+    }
+
+    fn light_mut(&mut self, kind: LightKind) -> &mut [u8] {
+        match kind {
+            LightKind::Sky => &mut self.sky_light,
+            LightKind::Block => &mut self.block_light,
+        }
+    }
+
+    pub fn get_light_local(&self, local: IVec3, kind: LightKind) -> Option<u8> {
+        self.get_local(local)?;
+        Some(self.light(kind)[Self::to_index(local)])
+    }
+
+    // light of the chunk on its own, as if there was nothing around it.
+    // light coming in from the neighbours is added once it's spawned, see propagate_light
+    pub fn compute_light(&mut self) {
+        for kind in LIGHT_KINDS {
+            self.light_mut(kind).fill(0);
+
+            let mut queue = VecDeque::new();
+
+            for x in 0..CHUNK_SIZE as i32 {
+                for z in 0..CHUNK_SIZE as i32 {
+                    for y in (0..CHUNK_HEIGHT as i32).rev() {
+                        let pos = IVec3::new(x, y, z);
+                        let index = Self::to_index(pos);
+                        let block = self.blocks[index];
+
+                        let level = match kind {
+                            // the open column down to the first block that takes anything away
+                            LightKind::Sky if block.light_opacity() > 0 => break,
+                            LightKind::Sky => MAX_LIGHT,
+                            LightKind::Block => block.light_emission(),
+                        };
+
+                        if level > 0 {
+                            self.light_mut(kind)[index] = level;
+                            queue.push_back(pos);
+                        }
+                    }
+                }
+            }
+
+            while let Some(pos) = queue.pop_front() {
+                let level = self.light(kind)[Self::to_index(pos)];
+
+                for dir in DIRECTIONS {
+                    let next = pos + dir.normal();
+
+                    let Some(block) = self.get_local(next) else {
+                        continue;
+                    };
+
+                    let index = Self::to_index(next);
+                    let light = spread(kind, level, dir, block);
+
+                    if light > self.light(kind)[index] {
+                        self.light_mut(kind)[index] = light;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl LightVolume<'_, '_, '_, '_> {
+    fn chunk(&self, world_pos: IVec3) -> Option<(&Chunk, IVec3)> {
+        let (coord, local) = split_world_pos(world_pos);
+        let entity = self.map.0.get(&coord)?;
+
+        Some((self.chunks.get(*entity).ok()?, local))
+    }
+
+    fn block(&self, world_pos: IVec3) -> Option<BlockType> {
+        let (chunk, local) = self.chunk(world_pos)?;
+        chunk.get_local(local)
+    }
+
+    // above the world is open sky, below it and unloaded chunks are dark
+    fn light(&self, world_pos: IVec3, kind: LightKind) -> Option<u8> {
+        if world_pos.y >= CHUNK_HEIGHT as i32 {
+            return Some(match kind {
+                LightKind::Sky => MAX_LIGHT,
+                LightKind::Block => 0,
+            });
+        }
+
+        let (chunk, local) = self.chunk(world_pos)?;
+        chunk.get_light_local(local, kind)
+    }
+
+    fn set_light(&mut self, world_pos: IVec3, kind: LightKind, level: u8) {
+        if world_pos.y < 0 || world_pos.y >= CHUNK_HEIGHT as i32 {
+            return;
+        }
+
+        let (coord, local) = split_world_pos(world_pos);
+        let Some(entity) = self.map.0.get(&coord) else {
+            return;
+        };
+        let Ok(mut chunk) = self.chunks.get_mut(*entity) else {
+            return;
+        };
+
+        chunk.light_mut(kind)[Chunk::to_index(local)] = level;
+
+        // blocks on the edge also light the faces of the chunks next to them
+        self.touched.extend(chunks_touching(coord, local));
+    }
+
+    // takes the light away from every block that got it through `removed`, blocks still lit
+    // from somewhere else end up in `refill` so their light can flow back in
+    fn remove(
+        &mut self,
+        kind: LightKind,
+        mut removed: VecDeque<(IVec3, u8)>,
+        refill: &mut VecDeque<IVec3>,
+    ) {
+        while let Some((pos, level)) = removed.pop_front() {
+            for dir in DIRECTIONS {
+                let next = pos + dir.normal();
+
+                let (Some(light), Some(block)) = (self.light(next, kind), self.block(next)) else {
+                    continue;
+                };
+                if light == 0 {
+                    continue;
+                }
+
+                if light < level
+                    || (light == MAX_LIGHT && spread(kind, level, dir, block) == MAX_LIGHT)
+                {
+                    removed.push_back((next, light));
+
+                    // light sources keep their own light whatever reached them before
+                    let emission = match kind {
+                        LightKind::Sky => 0,
+                        LightKind::Block => block.light_emission(),
+                    };
+
+                    self.set_light(next, kind, emission);
+                    if emission > 0 {
+                        refill.push_back(next);
+                    }
+                } else {
+                    refill.push_back(next);
+                }
+            }
+        }
+    }
+
+    fn spread(&mut self, kind: LightKind, mut queue: VecDeque<IVec3>) {
+        while let Some(pos) = queue.pop_front() {
+            let Some(level) = self.light(pos, kind) else {
+                continue;
+            };
+            if level <= 1 {
+                continue;
+            }
+
+            for dir in DIRECTIONS {
+                let next = pos + dir.normal();
+
+                let (Some(current), Some(block)) = (self.light(next, kind), self.block(next))
+                else {
+                    continue;
+                };
+
+                let light = spread(kind, level, dir, block);
+
+                if light > current {
+                    self.set_light(next, kind, light);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    // border blocks whose light would spread across into the neighbouring chunk
+    fn seed_borders(&self, coord: IVec2, kind: LightKind, queue: &mut VecDeque<IVec3>) {
+        let size = CHUNK_SIZE as i32;
+        let origin = coord * size;
+
+        for offset in [IVec2::NEG_X, IVec2::X, IVec2::NEG_Y, IVec2::Y] {
+            if !self.map.0.contains_key(&(coord + offset)) {
+                continue;
+            }
+
+            for i in 0..size {
+                // the column inside us and the one across the border
+                let (x, z) = match offset {
+                    IVec2::NEG_X => (0, i),
+                    IVec2::X => (size - 1, i),
+                    IVec2::NEG_Y => (i, 0),
+                    _ => (i, size - 1),
+                };
+
+                let inside = IVec2::new(origin.x + x, origin.y + z);
+                let outside = inside + offset;
+
+                for y in 0..CHUNK_HEIGHT as i32 {
+                    let a = inside.extend(y).xzy();
+                    let b = outside.extend(y).xzy();
+
+                    let (Some(la), Some(lb)) = (self.light(a, kind), self.light(b, kind)) else {
+                        continue;
+                    };
+
+                    if la.abs_diff(lb) > 1 {
+                        queue.push_back(a);
+                        queue.push_back(b);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// brings sky and block light up to date with changed blocks and spreads it between freshly
+// spawned chunks and their neighbours, then remeshes every chunk whose light changed
+pub fn propagate_light(
+    mut commands: Commands,
+    mut updates: ResMut<LightUpdates>,
+    map: Res<ChunkMap>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    if updates.blocks.is_empty() && updates.chunks.is_empty() {
+        return;
+    }
+
+    let mut volume = LightVolume {
+        chunks: &mut chunks,
+        map: &map,
+        touched: HashSet::new(),
+    };
+
+    for kind in LIGHT_KINDS {
+        let mut removed = VecDeque::new();
+        let mut refill = VecDeque::new();
+
+        // every changed block starts out dark, whatever still reaches it flows back in
+        for pos in &updates.blocks {
+            let Some(level) = volume.light(*pos, kind) else {
+                continue;
+            };
+
+            if level > 0 {
+                volume.set_light(*pos, kind, 0);
+                removed.push_back((*pos, level));
+            }
+
+            for dir in DIRECTIONS {
+                refill.push_back(*pos + dir.normal());
+            }
+        }
+
+        volume.remove(kind, removed, &mut refill);
+
+        // new light sources
+        if kind == LightKind::Block {
+            for pos in &updates.blocks {
+                let emission = volume.block(*pos).map_or(0, |block| block.light_emission());
+
+                if emission > 0 {
+                    volume.set_light(*pos, kind, emission);
+                    refill.push_back(*pos);
+                }
+            }
+        }
+
+        for coord in &updates.chunks {
+            volume.seed_borders(*coord, kind, &mut refill);
+        }
+
+        volume.spread(kind, refill);
+    }
+
+    updates.blocks.clear();
+    updates.chunks.clear();
+
+    for coord in volume.touched {
+        if let Some(entity) = map.0.get(&coord) {
+            commands.entity(*entity).insert(RemeshChunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    const COORDS: [IVec2; 2] = [IVec2::ZERO, IVec2::X];
+
+    // two empty chunks next to each other along x
+    fn world() -> World {
+        let mut world = World::new();
+        let mut map = ChunkMap::default();
+        let mut updates = LightUpdates::default();
+
+        for coord in COORDS {
+            let mut chunk = Chunk::empty(coord);
+            chunk.compute_light();
+
+            map.0.insert(coord, world.spawn(chunk).id());
+            updates.chunk_spawned(coord);
+        }
+
+        world.insert_resource(map);
+        world.insert_resource(updates);
+        world.run_system_once(propagate_light).unwrap();
+
+        world
+    }
+
+    fn set_block(world: &mut World, world_pos: IVec3, block: BlockType) {
+        let (coord, local) = split_world_pos(world_pos);
+        let entity = world.resource::<ChunkMap>().0[&coord];
+
+        world
+            .get_mut::<Chunk>(entity)
+            .unwrap()
+            .set_local(local, block);
+        world
+            .resource_mut::<LightUpdates>()
+            .block_changed(world_pos);
+
+        world.run_system_once(propagate_light).unwrap();
+    }
+
+    // block light of every block in both chunks with its world position
+    fn block_light(world: &mut World) -> Vec<(IVec3, u8)> {
+        let mut levels = Vec::new();
+
+        for chunk in world.query::<&Chunk>().iter(world) {
+            for x in 0..CHUNK_SIZE as i32 {
+                for y in 0..CHUNK_HEIGHT as i32 {
+                    for z in 0..CHUNK_SIZE as i32 {
+                        let local = IVec3::new(x, y, z);
+                        let level = chunk.get_light_local(local, LightKind::Block).unwrap();
+
+                        levels.push((chunk.chunk_origin() + local, level));
+                    }
+                }
+            }
+        }
+
+        levels
+    }
+
+    #[test]
+    fn torch_light_falls_off_and_goes_away_with_it() {
+        let mut world = world();
+
+        // on the last column of the first chunk, so half its light crosses the border
+        let torch = IVec3::new(CHUNK_SIZE as i32 - 1, 40, 6);
+        set_block(&mut world, torch, BlockType::Torch);
+
+        let levels = block_light(&mut world);
+        let emission = BlockType::Torch.light_emission();
+
+        for (pos, level) in &levels {
+            let distance = (*pos - torch).abs().element_sum();
+            let expected = (emission as i32 - distance).max(0) as u8;
+
+            assert_eq!(*level, expected, "at {pos}");
+        }
+
+        let across = levels
+            .iter()
+            .filter(|(pos, level)| pos.x >= CHUNK_SIZE as i32 && *level > 0)
+            .count();
+        assert!(across > 0);
+
+        set_block(&mut world, torch, BlockType::Air);
+
+        for (pos, level) in block_light(&mut world) {
+            assert_eq!(level, 0, "at {pos}");
+        }
+    }
+}
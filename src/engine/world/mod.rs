@@ -1,8 +1,19 @@
 mod biome;
+pub mod biome_tint;
 mod biomes;
 pub mod block;
+mod caves;
 mod chunk;
 pub mod chunk_meshing;
 mod climate_sampler;
+mod fluid_mesher;
+pub mod fog;
+pub mod generator;
+mod light;
+mod ores;
+mod pending_writes;
+pub mod seed;
+mod sprite_mesher;
 pub mod streaming;
 pub mod structure;
+mod structures;
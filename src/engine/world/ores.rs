@@ -0,0 +1,183 @@
+use bevy::math::{IVec2, IVec3};
+use rand::rngs::SmallRng;
+use rand::{RngExt, SeedableRng};
+
+use super::block::BlockType;
+use super::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunk};
+use super::structure::hash_2d;
+
+#[derive(Copy, Clone)]
+pub enum HeightDistribution {
+    Uniform { min: i32, max: i32 },
+    // most likely halfway between min and max, fading linearly towards both ends
+    Triangle { min: i32, max: i32 },
+}
+
+pub struct OreFeature {
+    pub veins_per_chunk: u32,
+    pub vein_size: u32,
+    pub height: HeightDistribution,
+    // (block that may be replaced, ore it turns into)
+    pub replaces: &'static [(BlockType, BlockType)],
+}
+
+pub static ORES: [OreFeature; 7] = [
+    OreFeature {
+        veins_per_chunk: 18,
+        vein_size: 12,
+        height: HeightDistribution::Triangle { min: 20, max: 124 },
+        replaces: &[
+            (BlockType::Stone, BlockType::CoalOre),
+            (BlockType::Deepslate, BlockType::DeepslateCoalOre),
+        ],
+    },
+    OreFeature {
+        veins_per_chunk: 12,
+        vein_size: 9,
+        height: HeightDistribution::Triangle { min: 0, max: 80 },
+        replaces: &[
+            (BlockType::Stone, BlockType::CopperOre),
+            (BlockType::Deepslate, BlockType::DeepslateCopperOre),
+        ],
+    },
+    OreFeature {
+        veins_per_chunk: 10,
+        vein_size: 8,
+        height: HeightDistribution::Triangle { min: 0, max: 72 },
+        replaces: &[
+            (BlockType::Stone, BlockType::IronOre),
+            (BlockType::Deepslate, BlockType::DeepslateIronOre),
+        ],
+    },
+    OreFeature {
+        veins_per_chunk: 2,
+        vein_size: 6,
+        height: HeightDistribution::Triangle { min: 0, max: 40 },
+        replaces: &[
+            (BlockType::Stone, BlockType::LapisOre),
+            (BlockType::Deepslate, BlockType::DeepslateLapisOre),
+        ],
+    },
+    OreFeature {
+        veins_per_chunk: 4,
+        vein_size: 7,
+        height: HeightDistribution::Triangle { min: 0, max: 36 },
+        replaces: &[
+            (BlockType::Stone, BlockType::GoldOre),
+            (BlockType::Deepslate, BlockType::DeepslateGoldOre),
+        ],
+    },
+    OreFeature {
+        veins_per_chunk: 6,
+        vein_size: 7,
+        height: HeightDistribution::Uniform { min: 1, max: 20 },
+        replaces: &[
+            (BlockType::Stone, BlockType::RedstoneOre),
+            (BlockType::Deepslate, BlockType::DeepslateRedstoneOre),
+        ],
+    },
+    OreFeature {
+        veins_per_chunk: 5,
+        vein_size: 5,
+        // peaks below the world floor, so diamonds get rarer the higher you go
+        height: HeightDistribution::Triangle { min: -12, max: 20 },
+        replaces: &[
+            (BlockType::Stone, BlockType::DiamondOre),
+            (BlockType::Deepslate, BlockType::DeepslateDiamondOre),
+        ],
+    },
+];
+
+impl HeightDistribution {
+    fn sample(&self, rng: &mut SmallRng) -> i32 {
+        match *self {
+            Self::Uniform { min, max } => rng.random_range(min..=max),
+            Self::Triangle { min, max } => {
+                let half = (max - min) / 2;
+                min + rng.random_range(0..=half) + rng.random_range(0..=(max - min - half))
+            }
+        }
+    }
+}
+
+impl OreFeature {
+    // vein blocks in world space for the veins seeded by chunk `coord`.
+    // may reach into neighbouring chunks
+    fn veins(&self, index: usize, coord: IVec2, seed: u32) -> Vec<IVec3> {
+        let mut rng = chunk_rng(coord, seed, index as u32);
+        let origin = IVec3::new(coord.x * CHUNK_SIZE as i32, 0, coord.y * CHUNK_SIZE as i32);
+
+        let mut blocks = Vec::with_capacity((self.veins_per_chunk * self.vein_size) as usize);
+
+        for _ in 0..self.veins_per_chunk {
+            let start = origin
+                + IVec3::new(
+                    rng.random_range(0..CHUNK_SIZE as i32),
+                    self.height.sample(&mut rng),
+                    rng.random_range(0..CHUNK_SIZE as i32),
+                );
+
+            // grow a blob by budding off random blocks that are already part of the vein
+            let first = blocks.len();
+            blocks.push(start);
+
+            for _ in 1..self.vein_size {
+                let parent = blocks[rng.random_range(first..blocks.len())];
+                let step = match rng.random_range(0..6) {
+                    0 => IVec3::X,
+                    1 => IVec3::NEG_X,
+                    2 => IVec3::Y,
+                    3 => IVec3::NEG_Y,
+                    4 => IVec3::Z,
+                    _ => IVec3::NEG_Z,
+                };
+
+                blocks.push(parent + step);
+            }
+        }
+
+        blocks
+    }
+
+    fn replacement(&self, block: BlockType) -> Option<BlockType> {
+        self.replaces
+            .iter()
+            .find(|(from, _)| *from == block)
+            .map(|(_, ore)| *ore)
+    }
+}
+
+// every chunk also replays the veins of its neighbours, so veins crossing a border
+// come out the same on both sides no matter which chunk generates first
+pub fn place_ores(chunk: &mut Chunk, seed: u32) {
+    let origin = chunk.chunk_origin();
+
+    for (index, ore) in ORES.iter().enumerate() {
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                let source = chunk.coord + IVec2::new(dx, dz);
+
+                for world_pos in ore.veins(index, source, seed) {
+                    let local = world_pos - origin;
+
+                    if local.y < 1 || local.y >= CHUNK_HEIGHT as i32 {
+                        continue;
+                    }
+
+                    let Some(current) = chunk.get_local(local) else {
+                        continue;
+                    };
+
+                    if let Some(replacement) = ore.replacement(current) {
+                        chunk.set_local(local, replacement);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn chunk_rng(coord: IVec2, seed: u32, salt: u32) -> SmallRng {
+    let h = hash_2d(coord.x, coord.y, seed ^ salt.wrapping_mul(0x9E3779B9));
+    SmallRng::seed_from_u64(((seed as u64) << 32) | h as u64)
+}
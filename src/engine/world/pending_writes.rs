@@ -0,0 +1,154 @@
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+
+use super::block::BlockType;
+use super::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunk, ChunkMap};
+use super::chunk_meshing::RemeshChunk;
+use super::light::LightUpdates;
+
+// block writes aimed at chunks that weren't available when they were issued.
+// keyed by chunk coord, then by local position so repeated writes collapse.
+#[derive(Resource, Default)]
+pub struct PendingBlockWrites(HashMap<IVec2, HashMap<IVec3, BlockType>>);
+
+// what each loaded chunk's structures wrote into its neighbours, keyed by that chunk.
+// kept until it unloads, so a neighbour regenerated in the meantime gets them again
+#[derive(Resource, Default)]
+pub struct StructureSpills(HashMap<IVec2, PendingBlockWrites>);
+
+impl PendingBlockWrites {
+    pub fn push(&mut self, world_pos: IVec3, block_type: BlockType) {
+        if world_pos.y < 0 || world_pos.y >= CHUNK_HEIGHT as i32 {
+            return;
+        }
+
+        let (coord, local) = split_world_pos(world_pos);
+
+        self.0.entry(coord).or_default().insert(local, block_type);
+    }
+
+    pub fn take(&mut self, coord: IVec2) -> Option<HashMap<IVec3, BlockType>> {
+        self.0.remove(&coord)
+    }
+
+    // later writes win, same as if they had been pushed one by one
+    pub fn extend(&mut self, coord: IVec2, writes: impl IntoIterator<Item = (IVec3, BlockType)>) {
+        self.0.entry(coord).or_default().extend(writes);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&IVec2, &HashMap<IVec3, BlockType>)> {
+        self.0.iter()
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(IVec2) -> bool) {
+        self.0.retain(|coord, _| keep(*coord));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl StructureSpills {
+    pub fn insert(&mut self, source: IVec2, spill: PendingBlockWrites) {
+        self.0.insert(source, spill);
+    }
+
+    pub fn remove(&mut self, source: IVec2) {
+        self.0.remove(&source);
+    }
+
+    // everything the loaded chunks' structures put into `target`
+    pub fn writes_into(&self, target: IVec2) -> HashMap<IVec3, BlockType> {
+        let mut writes = HashMap::new();
+
+        for spill in self.0.values() {
+            if let Some(spilled) = spill.0.get(&target) {
+                writes.extend(spilled.iter().map(|(local, block)| (*local, *block)));
+            }
+        }
+
+        writes
+    }
+}
+
+// the chunk a block is in and the neighbours it borders, their meshes and light see it too.
+// may repeat a chunk
+pub fn chunks_touching(coord: IVec2, local: IVec3) -> impl Iterator<Item = IVec2> {
+    let edge = |v: i32| match v {
+        0 => -1,
+        v if v == CHUNK_SIZE as i32 - 1 => 1,
+        _ => 0,
+    };
+
+    let (dx, dz) = (edge(local.x), edge(local.z));
+
+    [0, dx]
+        .into_iter()
+        .flat_map(move |x| [0, dz].into_iter().map(move |z| coord + IVec2::new(x, z)))
+}
+
+pub fn split_world_pos(world_pos: IVec3) -> (IVec2, IVec3) {
+    let coord = IVec2::new(
+        world_pos.x.div_euclid(CHUNK_SIZE as i32),
+        world_pos.z.div_euclid(CHUNK_SIZE as i32),
+    );
+
+    let local = IVec3::new(
+        world_pos.x.rem_euclid(CHUNK_SIZE as i32),
+        world_pos.y,
+        world_pos.z.rem_euclid(CHUNK_SIZE as i32),
+    );
+
+    (coord, local)
+}
+
+// applies queued writes to chunks that have been spawned since they were queued
+// and flags them so the meshing system picks them up again
+pub fn flush_pending_writes(
+    mut commands: Commands,
+    mut pending: ResMut<PendingBlockWrites>,
+    mut light_updates: ResMut<LightUpdates>,
+    map: Res<ChunkMap>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    if pending.is_empty() {
+        return;
+    }
+
+    let ready: Vec<IVec2> = pending
+        .0
+        .keys()
+        .filter(|coord| {
+            map.0
+                .get(*coord)
+                .is_some_and(|entity| chunks.contains(*entity))
+        })
+        .copied()
+        .collect();
+
+    let mut remesh = HashSet::new();
+
+    for coord in ready {
+        let entity = map.0[&coord];
+        let Ok(mut chunk) = chunks.get_mut(entity) else {
+            continue;
+        };
+
+        if let Some(writes) = pending.take(coord) {
+            let origin = chunk.chunk_origin();
+            for local in writes.keys() {
+                light_updates.block_changed(origin + *local);
+                remesh.extend(chunks_touching(coord, *local));
+            }
+
+            chunk.apply_writes(writes);
+        }
+    }
+
+    for coord in remesh {
+        if let Some(entity) = map.0.get(&coord) {
+            commands.entity(*entity).insert(RemeshChunk);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+const DEFAULT_SEED: i64 = 42;
+
+// salts for every generator that draws from the world seed.
+// changing one of these changes every world, so don't
+pub const SALT_HEIGHT: u32 = 0;
+pub const SALT_DENSITY: u32 = 1;
+pub const SALT_CLIFF: u32 = 2;
+pub const SALT_STRUCTURES: u32 = 10;
+pub const SALT_ORES: u32 = 11;
+pub const SALT_LAYERS: u32 = 12;
+pub const SALT_TEMPERATURE: u32 = 20;
+pub const SALT_MOISTURE: u32 = 21;
+pub const SALT_RIVER: u32 = 22;
+pub const SALT_RIVER_WIDTH: u32 = 23;
+pub const SALT_PLAINS: u32 = 100;
+pub const SALT_DESERT: u32 = 200;
+pub const SALT_JUNGLE: u32 = 300;
+pub const SALT_TUNDRA: u32 = 400;
+pub const SALT_CAVES: u32 = 500;
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WorldSeed(pub i64);
+
+impl Default for WorldSeed {
+    fn default() -> Self {
+        Self(DEFAULT_SEED)
+    }
+}
+
+impl WorldSeed {
+    // numbers are taken as they are, anything else goes through java's String.hashCode
+    // like minecraft does, so seeds people share keep working the same way
+    pub fn from_text(text: &str) -> WorldSeed {
+        let text = text.trim();
+
+        if let Ok(number) = text.parse::<i64>() {
+            return WorldSeed(number);
+        }
+
+        let hash = text
+            .encode_utf16()
+            .fold(0i32, |h, c| h.wrapping_mul(31).wrapping_add(c as i32));
+
+        WorldSeed(hash as i64)
+    }
+
+    // `--seed <value>` or `--seed=<value>`
+    pub fn from_args() -> Option<WorldSeed> {
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            if let Some(value) = arg.strip_prefix("--seed=") {
+                return Some(Self::from_text(value));
+            }
+            if arg == "--seed" {
+                return args.next().map(|value| Self::from_text(&value));
+            }
+        }
+
+        None
+    }
+
+    // 32 bit seed for a single generator. noise's fbm seeds its octaves with seed + 1,
+    // seed + 2, ... so the salt can't just be added on top, close seeds would share octaves
+    pub fn derive(&self, salt: u32) -> u32 {
+        // splitmix64, the salt picks how far along this world seed's sequence we are.
+        // neighbouring world seeds or salts end up far apart
+        let mut z =
+            (self.0 as u64).wrapping_add((salt as u64 + 1).wrapping_mul(0x9E3779B97F4A7C15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+
+        // top bit cleared, fbm adds the octave to the seed without wrapping
+        (z as u32 ^ (z >> 32) as u32) >> 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SALTS: [u32; 15] = [
+        SALT_HEIGHT,
+        SALT_DENSITY,
+        SALT_CLIFF,
+        SALT_STRUCTURES,
+        SALT_ORES,
+        SALT_LAYERS,
+        SALT_TEMPERATURE,
+        SALT_MOISTURE,
+        SALT_RIVER,
+        SALT_RIVER_WIDTH,
+        SALT_PLAINS,
+        SALT_DESERT,
+        SALT_JUNGLE,
+        SALT_TUNDRA,
+        SALT_CAVES,
+    ];
+
+    // more than any generator has octaves
+    const OCTAVE_SPAN: u32 = 16;
+
+    #[test]
+    fn derived_seeds_dont_share_octaves() {
+        for world in [0, 1, 42, -1, i64::MIN, i64::MAX] {
+            let seeds = SALTS.map(|salt| WorldSeed(world).derive(salt));
+
+            for (i, a) in seeds.iter().enumerate() {
+                assert!(a.checked_add(OCTAVE_SPAN).is_some(), "{world} {i}");
+
+                for b in &seeds[i + 1..] {
+                    assert!(a.abs_diff(*b) >= OCTAVE_SPAN, "{world} {a} {b}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use bevy::math::{IVec3, Vec3};
+
+use super::block::BlockType;
+use super::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunk, PAD_CHUNK_VOLUME, PaddedLight};
+use super::light::{self, LightKind};
+use crate::engine::atlas::TextureAtlas;
+use crate::engine::face_direction::FaceDirection;
+use crate::engine::mesh_builder::MeshBuilder;
+
+// the two diagonals through a block, in the xz plane
+const DIAGONALS: [Vec3; 2] = [Vec3::new(1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0)];
+
+// sprites like torches aren't cubes, they get two quads crossing diagonally through their
+// block instead, drawn from both sides. lit by the light inside their own block
+pub fn build_sprite_mesh(
+    padded: &[BlockType; PAD_CHUNK_VOLUME],
+    light: &PaddedLight,
+    atlas: &TextureAtlas,
+    builder: &mut MeshBuilder,
+) {
+    for y in 0..CHUNK_HEIGHT as i32 {
+        for z in 0..CHUNK_SIZE as i32 {
+            for x in 0..CHUNK_SIZE as i32 {
+                let pos = IVec3::new(x, y, z);
+                let padded_pos = pos + IVec3::ONE;
+                let index = Chunk::pad_index(padded_pos.x, padded_pos.y, padded_pos.z);
+                let sprite = padded[index];
+
+                if !sprite.is_sprite() {
+                    continue;
+                }
+
+                let Some(texture_id) = sprite.texture_id(FaceDirection::Front) else {
+                    continue;
+                };
+                let tile = atlas.tile_origin(texture_id);
+
+                let (brightness, block_share) = light::vertex_light(
+                    light[LightKind::Sky as usize][index] as f32,
+                    light[LightKind::Block as usize][index] as f32,
+                );
+                let color = [brightness, brightness, brightness, block_share];
+
+                let center = pos.as_vec3();
+
+                for diagonal in DIAGONALS {
+                    let half = diagonal * 0.5;
+                    let up = Vec3::new(0.0, 0.5, 0.0);
+
+                    // bottom and top of either end, going around like FACE_VERTICES
+                    let a = (center - half - up).to_array();
+                    let b = (center + half - up).to_array();
+                    let c = (center + half + up).to_array();
+                    let d = (center - half + up).to_array();
+
+                    let normal = diagonal.cross(Vec3::Y).normalize();
+
+                    builder.add_shaped_quad([a, b, c, d], normal.to_array(), tile, color);
+                    // the back, mirrored so it isn't upside down
+                    builder.add_shaped_quad([b, a, d, c], (-normal).to_array(), tile, color);
+                }
+            }
+        }
+    }
+}
@@ -2,7 +2,16 @@ use crate::engine::atlas::BlockAtlas;
 use crate::engine::atlas::ChunkMaterial;
 use crate::engine::world::chunk::Chunk;
 use crate::engine::world::chunk_meshing::UnmeshedChunk;
-use bevy::{platform::collections::HashSet, prelude::*};
+use crate::engine::world::generator::{SharedWorldGenerator, rebuild_world_generator};
+use crate::engine::world::light::{LightUpdates, propagate_light};
+use crate::engine::world::pending_writes::{
+    PendingBlockWrites, StructureSpills, flush_pending_writes,
+};
+use crate::engine::world::seed::WorldSeed;
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on};
+use futures_lite::future;
 
 use crate::engine::world::chunk::ChunkMap;
 
@@ -13,8 +22,8 @@ pub struct StreamingPlugin {
 }
 
 #[derive(Resource)]
-struct StreamingResource {
-    render_distance: usize,
+pub struct StreamingResource {
+    pub render_distance: usize,
 }
 
 #[derive(Eq, PartialEq, Hash, Clone, Copy)]
@@ -47,6 +56,22 @@ struct PromoteQueue {
     list: Vec<IVec2>,
 }
 
+struct GeneratedChunk {
+    chunk: Chunk,
+    // writes the chunk's structures made into its neighbours
+    spill: PendingBlockWrites,
+}
+
+struct GeneratingChunk {
+    task: Task<GeneratedChunk>,
+    should_be_meshed: bool,
+}
+
+// chunks being generated in the background, they only get an entity once done.
+// dropping an entry cancels its task
+#[derive(Resource, Default)]
+struct GeneratingChunks(HashMap<IVec2, GeneratingChunk>);
+
 impl Default for StreamingPlugin {
     fn default() -> Self {
         Self {
@@ -61,20 +86,28 @@ impl Plugin for StreamingPlugin {
             render_distance: self.render_distance,
         });
 
+        app.init_resource::<WorldSeed>();
         app.insert_resource(PlayerChunkPositionTracker::default());
         app.insert_resource(DesiredChunks::default());
 
         app.insert_resource(SpawnQueue::default());
         app.insert_resource(PromoteQueue::default());
         app.insert_resource(DespawnQueue::default());
+        app.insert_resource(GeneratingChunks::default());
+        app.insert_resource(StructureSpills::default());
 
         app.add_systems(
             Update,
             (
+                rebuild_world_generator.run_if(resource_changed::<WorldSeed>),
                 detect_player_chunk,
                 update_desired_chunk_set.run_if(resource_changed::<PlayerChunkPositionTracker>),
                 reconcile_chunks.run_if(resource_changed::<DesiredChunks>),
-                execute_spawns.run_if(resource_exists::<BlockAtlas>),
+                cancel_stale_generation.run_if(resource_changed::<DesiredChunks>),
+                execute_spawns.run_if(resource_exists::<SharedWorldGenerator>),
+                finish_generation.run_if(resource_exists::<BlockAtlas>),
+                flush_pending_writes,
+                propagate_light,
                 execute_promotions,
                 execute_despawns,
             )
@@ -134,6 +167,7 @@ fn update_desired_chunk_set(
 fn reconcile_chunks(
     desired: Res<DesiredChunks>,
     chunk_map: Res<ChunkMap>,
+    mut generating: ResMut<GeneratingChunks>,
     mut spawn: ResMut<SpawnQueue>,
     mut despawn: ResMut<DespawnQueue>,
     mut promote: ResMut<PromoteQueue>,
@@ -150,9 +184,11 @@ fn reconcile_chunks(
 
     for entry in desired {
         match chunk_map.get(&entry.coord) {
-            None => {
-                spawn.list.push(*entry);
-            }
+            None => match generating.0.get_mut(&entry.coord) {
+                // still in flight, just make sure it ends up meshed or not as wanted now
+                Some(in_flight) => in_flight.should_be_meshed = entry.should_be_meshed,
+                None => spawn.list.push(*entry),
+            },
 
             Some(&entity) => {
                 if entry.should_be_meshed && unmeshed.contains(entity) {
@@ -169,24 +205,108 @@ fn reconcile_chunks(
     }
 }
 
+fn cancel_stale_generation(
+    desired: Res<DesiredChunks>,
+    mut generating: ResMut<GeneratingChunks>,
+    mut pending: ResMut<PendingBlockWrites>,
+) {
+    let desired_coords: HashSet<_> = desired.0.iter().map(|e| e.coord).collect();
+
+    generating
+        .0
+        .retain(|coord, _| desired_coords.contains(coord));
+
+    // writes for chunks out of range would never be applied, the chunks get regenerated anyway
+    pending.retain(|coord| desired_coords.contains(&coord));
+}
+
 fn execute_spawns(
-    mut commands: Commands,
     mut spawn: ResMut<SpawnQueue>,
-    mut map: ResMut<ChunkMap>,
-    chunk_material: Res<ChunkMaterial>,
+    mut generating: ResMut<GeneratingChunks>,
+    generator: Res<SharedWorldGenerator>,
 ) {
+    let pool = AsyncComputeTaskPool::get();
     let count = spawn.list.len();
 
     for _ in 0..count {
         if let Some(entry) = spawn.list.pop() {
-            let entity = Chunk::new_entity(&mut commands, &chunk_material, entry.coord);
+            if generating.0.contains_key(&entry.coord) {
+                continue;
+            }
+
+            let generator = generator.0.clone();
+            let coord = entry.coord;
+
+            let task = pool.spawn(async move {
+                let mut spill = PendingBlockWrites::default();
+                let chunk = Chunk::new(coord.x, coord.y, &generator, &mut spill);
+
+                GeneratedChunk { chunk, spill }
+            });
 
-            if !entry.should_be_meshed {
-                commands.entity(entity).insert(UnmeshedChunk);
+            generating.0.insert(
+                entry.coord,
+                GeneratingChunk {
+                    task,
+                    should_be_meshed: entry.should_be_meshed,
+                },
+            );
+        }
+    }
+}
+
+fn finish_generation(
+    mut commands: Commands,
+    mut generating: ResMut<GeneratingChunks>,
+    mut map: ResMut<ChunkMap>,
+    mut pending: ResMut<PendingBlockWrites>,
+    mut spills: ResMut<StructureSpills>,
+    mut light_updates: ResMut<LightUpdates>,
+    chunk_material: Res<ChunkMaterial>,
+) {
+    let mut finished = Vec::new();
+
+    for (coord, in_flight) in generating.0.iter_mut() {
+        if let Some(generated) = block_on(future::poll_once(&mut in_flight.task)) {
+            finished.push((*coord, in_flight.should_be_meshed, generated));
+        }
+    }
+
+    for (coord, should_be_meshed, generated) in finished {
+        generating.0.remove(&coord);
+
+        let GeneratedChunk { mut chunk, spill } = generated;
+
+        // structures of loaded neighbours reaching into us, and writes queued before we existed
+        let mut writes = spills.writes_into(coord);
+        if let Some(queued) = pending.take(coord) {
+            writes.extend(queued);
+        }
+
+        if !writes.is_empty() {
+            chunk.apply_writes(writes);
+            chunk.compute_light();
+        }
+
+        // loaded neighbours get our structures right away, the rest once they spawn
+        for (target, writes) in spill.iter() {
+            if map.0.contains_key(target) {
+                pending.extend(
+                    *target,
+                    writes.iter().map(|(local, block)| (*local, *block)),
+                );
             }
+        }
+        spills.insert(coord, spill);
+        light_updates.chunk_spawned(coord);
+
+        let entity = Chunk::new_entity(&mut commands, &chunk_material, chunk);
 
-            map.0.insert(entry.coord, entity);
+        if !should_be_meshed {
+            commands.entity(entity).insert(UnmeshedChunk);
         }
+
+        map.0.insert(coord, entity);
     }
 }
 
@@ -194,11 +314,15 @@ fn execute_despawns(
     mut commands: Commands,
     mut despawn: ResMut<DespawnQueue>,
     mut map: ResMut<ChunkMap>,
+    mut spills: ResMut<StructureSpills>,
 ) {
     let count = despawn.list.len();
 
     for _ in 0..count {
         if let Some(coord) = despawn.list.pop() {
+            // regenerating it brings its structures back
+            spills.remove(coord);
+
             if let Some(entity) = map.0.remove(&coord) {
                 commands.entity(entity).despawn();
             }
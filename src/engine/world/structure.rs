@@ -6,7 +6,7 @@ pub struct StructureRule {
     pub rarity: f64, // spawn probability
     pub min_height: i32,
     pub max_height: i32,
-    pub generator: fn(IVec3, &mut dyn BlockWrite),
+    pub generator: fn(IVec3, &mut dyn BlockWrite, u32),
 }
 
 impl StructureRule {
@@ -23,12 +23,12 @@ impl StructureRule {
 
     pub fn try_place(&self, pos: IVec3, world: &mut dyn BlockWrite, seed: u32) {
         if self.should_place(pos.x, pos.z, pos.y, seed) {
-            (self.generator)(pos, world);
+            (self.generator)(pos, world, seed);
         }
     }
 }
 
-fn hash_2d(x: i32, z: i32, seed: u32) -> u32 {
+pub fn hash_2d(x: i32, z: i32, seed: u32) -> u32 {
     let mut h = seed ^ (x as u32).wrapping_mul(0x9E3779B9) ^ (z as u32).wrapping_mul(0x85EBCA6B);
 
     h ^= h >> 16;
@@ -39,3 +39,50 @@ fn hash_2d(x: i32, z: i32, seed: u32) -> u32 {
 
     h
 }
+
+// deterministic stream of rolls for a single structure instance.
+// salted so it doesn't correlate with the roll in `should_place`
+pub struct StructureRng {
+    origin: IVec3,
+    seed: u32,
+    counter: u32,
+}
+
+impl StructureRng {
+    pub fn new(origin: IVec3, seed: u32) -> Self {
+        Self {
+            origin,
+            seed: hash_2d(origin.y, 0x5EED, seed),
+            counter: 0,
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.counter += 1;
+        hash_2d(
+            self.origin.x,
+            self.origin.z,
+            self.seed ^ self.counter.wrapping_mul(0x27D4EB2F),
+        )
+    }
+
+    // inclusive on both ends
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        let span = (max - min + 1) as u32;
+        min + (self.next_u32() % span) as i32
+    }
+
+    pub fn chance(&mut self, p: f64) -> bool {
+        (self.next_u32() as f64) / (u32::MAX as f64) < p
+    }
+
+    // position bound roll, same answer for the same block no matter the call order
+    pub fn chance_at(&self, pos: IVec3, p: f64) -> bool {
+        let h = hash_2d(
+            pos.x,
+            pos.z,
+            self.seed ^ (pos.y as u32).wrapping_mul(0x165667B1),
+        );
+        (h as f64) / (u32::MAX as f64) < p
+    }
+}
@@ -0,0 +1,13 @@
+use bevy::math::IVec3;
+
+use crate::engine::world::block::{BlockType, BlockWrite};
+use crate::engine::world::structure::StructureRng;
+
+pub fn generate(origin: IVec3, world: &mut dyn BlockWrite, seed: u32) {
+    let mut rng = StructureRng::new(origin, seed);
+
+    let height = rng.range(1, 3);
+    for y in 0..height {
+        world.set_block(origin + IVec3::Y * y, BlockType::Cactus);
+    }
+}
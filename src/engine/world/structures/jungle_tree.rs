@@ -0,0 +1,88 @@
+use bevy::math::IVec3;
+
+use super::{HORIZONTAL, leaf_blob, leaf_disc};
+use crate::engine::world::block::{BlockType, BlockWrite};
+use crate::engine::world::structure::StructureRng;
+
+const CANOPY_RADIUS: i32 = 4;
+
+// the blocks directly around the 2x2 trunk, relative to `origin`
+const TRUNK_RING: [IVec3; 8] = [
+    IVec3::new(-1, 0, 0),
+    IVec3::new(-1, 0, 1),
+    IVec3::new(2, 0, 0),
+    IVec3::new(2, 0, 1),
+    IVec3::new(0, 0, -1),
+    IVec3::new(1, 0, -1),
+    IVec3::new(0, 0, 2),
+    IVec3::new(1, 0, 2),
+];
+
+pub fn generate(origin: IVec3, world: &mut dyn BlockWrite, seed: u32) {
+    let mut rng = StructureRng::new(origin, seed);
+
+    let height = rng.range(14, 22);
+    let top = origin + IVec3::Y * height;
+
+    leaf_disc(
+        world,
+        &rng,
+        top - IVec3::Y,
+        CANOPY_RADIUS + 1,
+        BlockType::JungleLeaf,
+    );
+    leaf_disc(world, &rng, top, CANOPY_RADIUS, BlockType::JungleLeaf);
+    leaf_disc(
+        world,
+        &rng,
+        top + IVec3::Y,
+        CANOPY_RADIUS - 2,
+        BlockType::JungleLeaf,
+    );
+
+    // a few smaller clumps sticking out of the trunk below the canopy
+    let clumps = rng.range(1, 3);
+    for _ in 0..clumps {
+        let dir = HORIZONTAL[rng.range(0, 3) as usize];
+        let y = rng.range(height / 2, height - 4);
+        let center = origin + IVec3::Y * y + dir * 2 + dir.max(IVec3::ZERO);
+
+        leaf_blob(world, &rng, center, 1, BlockType::JungleLeaf);
+    }
+
+    // vines hang from the rim of the lowest canopy layer
+    let rim = CANOPY_RADIUS + 2;
+    for dx in -rim..=rim {
+        for dz in -rim..=rim {
+            let d2 = dx * dx + dz * dz;
+            if d2 <= (rim - 1) * (rim - 1) || d2 > rim * rim {
+                continue;
+            }
+
+            let anchor = top - IVec3::Y + IVec3::new(dx, 0, dz);
+            if !rng.chance_at(anchor, 0.35) {
+                continue;
+            }
+
+            let length = rng.range(2, 7);
+            for i in 0..length {
+                world.set_block(anchor - IVec3::Y * i, BlockType::Vine);
+            }
+        }
+    }
+
+    for y in 0..height - 2 {
+        for offset in TRUNK_RING {
+            let pos = origin + offset + IVec3::Y * y;
+            if rng.chance_at(pos, 0.2) {
+                world.set_block(pos, BlockType::Vine);
+            }
+        }
+    }
+
+    for y in 0..height {
+        for (dx, dz) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            world.set_block(origin + IVec3::new(dx, y, dz), BlockType::JungleWood);
+        }
+    }
+}
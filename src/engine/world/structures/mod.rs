@@ -0,0 +1,67 @@
+use bevy::math::IVec3;
+
+use crate::engine::world::block::{BlockType, BlockWrite};
+use crate::engine::world::structure::StructureRng;
+
+pub mod cactus;
+pub mod jungle_tree;
+pub mod oak;
+pub mod spruce;
+
+const HORIZONTAL: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+// roughly spherical clump, rim blocks are thinned out so it doesn't look like a ball
+fn leaf_blob(
+    world: &mut dyn BlockWrite,
+    rng: &StructureRng,
+    center: IVec3,
+    radius: i32,
+    leaf: BlockType,
+) {
+    let r2 = radius * radius;
+
+    for dx in -radius..=radius {
+        for dy in -radius..=radius {
+            for dz in -radius..=radius {
+                let d2 = dx * dx + dy * dy + dz * dz;
+                if d2 > r2 + 1 {
+                    continue;
+                }
+
+                let pos = center + IVec3::new(dx, dy, dz);
+                if d2 >= r2 && !rng.chance_at(pos, 0.5) {
+                    continue;
+                }
+
+                world.set_block(pos, leaf);
+            }
+        }
+    }
+}
+
+// flat circular layer of leaves
+fn leaf_disc(
+    world: &mut dyn BlockWrite,
+    rng: &StructureRng,
+    center: IVec3,
+    radius: i32,
+    leaf: BlockType,
+) {
+    let r2 = radius * radius;
+
+    for dx in -radius..=radius {
+        for dz in -radius..=radius {
+            let d2 = dx * dx + dz * dz;
+            if d2 > r2 + 1 {
+                continue;
+            }
+
+            let pos = center + IVec3::new(dx, 0, dz);
+            if d2 > r2 - radius && !rng.chance_at(pos, 0.7) {
+                continue;
+            }
+
+            world.set_block(pos, leaf);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use bevy::math::IVec3;
+
+use super::{HORIZONTAL, leaf_blob};
+use crate::engine::world::block::{BlockType, BlockWrite};
+use crate::engine::world::structure::StructureRng;
+
+pub fn generate(origin: IVec3, world: &mut dyn BlockWrite, seed: u32) {
+    let mut rng = StructureRng::new(origin, seed);
+
+    let trunk_height = rng.range(5, 7);
+    let top = origin + IVec3::Y * trunk_height;
+
+    // branches leave the upper half of the trunk and step outwards and upwards
+    let branch_count = rng.range(1, 3);
+    let mut branch_logs = Vec::new();
+    let mut branch_ends = Vec::with_capacity(branch_count as usize);
+
+    for _ in 0..branch_count {
+        let dir = HORIZONTAL[rng.range(0, 3) as usize];
+        let start_y = rng.range(trunk_height / 2 + 1, trunk_height - 1);
+        let length = rng.range(2, 3);
+
+        let mut pos = origin + IVec3::Y * start_y;
+        for step in 0..length {
+            pos += if step % 2 == 0 { dir } else { dir + IVec3::Y };
+            branch_logs.push(pos);
+        }
+
+        branch_ends.push(pos);
+    }
+
+    leaf_blob(world, &rng, top, 2, BlockType::OakLeaf);
+    for end in branch_ends {
+        let radius = rng.range(1, 2);
+        leaf_blob(world, &rng, end + IVec3::Y, radius, BlockType::OakLeaf);
+    }
+
+    // wood goes in last so leaves never cut through it
+    for y in 0..trunk_height {
+        world.set_block(origin + IVec3::Y * y, BlockType::OakWood);
+    }
+    for pos in branch_logs {
+        world.set_block(pos, BlockType::OakWood);
+    }
+}
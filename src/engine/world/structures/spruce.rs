@@ -0,0 +1,63 @@
+use bevy::math::IVec3;
+
+use crate::engine::world::block::{BlockType, BlockWrite};
+use crate::engine::world::structure::StructureRng;
+
+const MAX_TIER_RADIUS: i32 = 3;
+
+pub fn generate(origin: IVec3, world: &mut dyn BlockWrite, seed: u32) {
+    let mut rng = StructureRng::new(origin, seed);
+
+    let height = rng.range(7, 11);
+    let leaf_start = rng.range(2, 3);
+
+    // radius per layer, index 0 is the layer at `leaf_start`
+    let radii: Vec<i32> = (leaf_start..=height)
+        .map(|y| tier_radius(height - y))
+        .collect();
+
+    for (i, &radius) in radii.iter().enumerate() {
+        let y = leaf_start + i as i32;
+        let above = radii.get(i + 1).copied();
+
+        for dx in -radius..=radius {
+            for dz in -radius..=radius {
+                if !in_tier(dx, dz, radius) {
+                    continue;
+                }
+
+                let pos = origin + IVec3::new(dx, y, dz);
+                world.set_block(pos, BlockType::SpruceLeaf);
+
+                // snow settles wherever the layer above doesn't cover this one
+                let covered = above.is_some_and(|r| in_tier(dx, dz, r));
+                if !covered && (dx, dz) != (0, 0) {
+                    world.set_block(pos + IVec3::Y, BlockType::Snow);
+                }
+            }
+        }
+    }
+
+    let tip = origin + IVec3::Y * (height + 1);
+    world.set_block(tip, BlockType::SpruceLeaf);
+    world.set_block(tip + IVec3::Y, BlockType::Snow);
+
+    for y in 0..height {
+        world.set_block(origin + IVec3::Y * y, BlockType::SpruceWood);
+    }
+}
+
+// layers alternate between wide and narrow and grow towards the bottom
+fn tier_radius(from_top: i32) -> i32 {
+    if from_top == 0 {
+        return 0;
+    }
+
+    let wide = (1 + from_top / 3).min(MAX_TIER_RADIUS);
+
+    if from_top % 2 == 0 { wide - 1 } else { wide }
+}
+
+fn in_tier(dx: i32, dz: i32, radius: i32) -> bool {
+    dx * dx + dz * dz <= radius * radius + radius / 2
+}
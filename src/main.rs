@@ -1,33 +1,32 @@
-mod debug;
-mod engine;
-
 use bevy::prelude::*;
 
-use engine::atlas::AtlasPlugin;
-use engine::camera::CameraPlugin;
-use engine::world::chunk_meshing::ChunkMeshingPlugin;
-use engine::world::streaming::StreamingPlugin;
+use minecraft_clone::engine::atlas::AtlasPlugin;
+use minecraft_clone::engine::camera::CameraPlugin;
+use minecraft_clone::engine::day_night::DayNightPlugin;
+use minecraft_clone::engine::resource_pack::ResourcePacks;
+use minecraft_clone::engine::world::chunk_meshing::ChunkMeshingPlugin;
+use minecraft_clone::engine::world::fog::FogPlugin;
+use minecraft_clone::engine::world::seed::WorldSeed;
+use minecraft_clone::engine::world::streaming::StreamingPlugin;
 
-use debug::wireframe::WireframeDebugPlugin;
+use minecraft_clone::debug::lighting::LightingDebugPlugin;
+use minecraft_clone::debug::mesher::MesherDebugPlugin;
+use minecraft_clone::debug::wireframe::WireframeDebugPlugin;
 
 fn main() {
+    let seed = WorldSeed::from_args().unwrap_or_default();
+
     App::new()
+        .insert_resource(seed)
+        .insert_resource(ResourcePacks::from_args())
         .add_plugins(DefaultPlugins)
         .add_plugins(AtlasPlugin)
         .add_plugins(CameraPlugin::default())
         .add_plugins(WireframeDebugPlugin::default())
+        .add_plugins(MesherDebugPlugin::default())
+        .add_plugins(LightingDebugPlugin::default())
         .add_plugins((ChunkMeshingPlugin, StreamingPlugin::default()))
-        .insert_resource(ClearColor(Color::srgb(0.52, 0.80, 0.92)))
-        .add_systems(Startup, spawn_light)
+        .add_plugins(DayNightPlugin::default())
+        .add_plugins(FogPlugin::default())
         .run();
 }
-
-fn spawn_light(mut commands: Commands) {
-    commands.spawn((
-        DirectionalLight {
-            illuminance: 40_000.0,
-            ..default()
-        },
-        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -1.2, -0.9, 0.0)),
-    ));
-}